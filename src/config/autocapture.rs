@@ -1,39 +1,186 @@
-use std::collections::HashMap;
-
+use camino::Utf8Path;
 use color_eyre::eyre::Result;
+use glob::{MatchOptions, Pattern};
 use serde_yaml::{Mapping, Value};
 
-use crate::util::path as path_util;
+use crate::{log_debug, log_warn, util::path as path_util};
+
+struct AutocaptureOptions {
+	depth: u64,
+	include: Vec<Pattern>,
+	exclude: Vec<Pattern>,
+	markers: Vec<String>,
+	// names nested directories after their whole path from the config directory, e.g. 'packages-api'
+	path_naming: bool,
+}
+
+impl Default for AutocaptureOptions {
+	fn default() -> Self {
+		AutocaptureOptions {
+			depth: 1,
+			include: Vec::new(),
+			exclude: Vec::new(),
+			markers: Vec::new(),
+			path_naming: false,
+		}
+	}
+}
+
+impl AutocaptureOptions {
+	// can use unwrap, as the options have already been validated in the expansion step
+	fn from_mapping(options: &Mapping) -> Self {
+		let get_strings = |key: &str| -> Vec<String> {
+			options
+				.get(key)
+				.and_then(Value::as_sequence)
+				.map(|items| {
+					items
+						.iter()
+						.map(|item| item.as_str().unwrap().to_string())
+						.collect()
+				})
+				.unwrap_or_default()
+		};
+
+		let get_patterns = |key: &str| -> Vec<Pattern> {
+			get_strings(key)
+				.iter()
+				.map(|pattern| Pattern::new(pattern).unwrap())
+				.collect()
+		};
+
+		AutocaptureOptions {
+			depth: options.get("depth").and_then(Value::as_u64).unwrap_or(1),
+			include: get_patterns("include"),
+			exclude: get_patterns("exclude"),
+			markers: get_strings("markers"),
+			path_naming: options.get("naming").and_then(Value::as_str) == Some("path"),
+		}
+	}
+}
 
 pub fn auto_capture_workspaces<'a>(
 	path: &str,
 	working_dir: &str,
 	workspaces: &'a mut Mapping,
 ) -> Result<&'a mut Mapping> {
-	println!("{path} - auto capturing workspaces");
+	let options = match workspaces.shift_remove("autocapture") {
+		Some(Value::Bool(false)) => {
+			log_debug!("{path} - auto capturing disabled");
 
-	let auto_captured_workspaces = path_util::get_sub_directories(working_dir)?
-		.into_iter()
-		.filter(|(key, _)| !key.starts_with('.'))
-		.collect::<HashMap<_, _>>();
+			return Ok(workspaces);
+		}
+		Some(Value::Mapping(options)) => AutocaptureOptions::from_mapping(&options),
+		_ => AutocaptureOptions::default(),
+	};
+
+	log_debug!("{path} - auto capturing workspaces");
+
+	let mut auto_captured_workspaces = Vec::new();
+	collect_workspaces(working_dir, "", 1, &options, &mut auto_captured_workspaces)?;
 
 	for (key, value) in auto_captured_workspaces {
 		let key = key.replace(' ', "-");
 
 		if workspaces.contains_key(key.clone())
-			|| workspaces.values().any(|v| v.as_str().unwrap() == value)
+			|| workspaces
+				.values()
+				.any(|v| v.get("path").and_then(Value::as_str) == Some(value.as_str()))
 		{
-			println!(
+			log_warn!(
 				"WARNING - conflicting key or value already present in user defined workspaces. skipping adding '{key}': '{value}'"
 			);
 			continue;
 		}
 
-		println!("{path} - auto captured '{key}' ({value})");
-		workspaces.insert(Value::String(key), Value::String(value));
+		log_debug!("{path} - auto captured '{key}' ({value})");
+		let mut workspace = Mapping::new();
+		workspace.insert(Value::String("path".to_string()), Value::String(value));
+
+		workspaces.insert(Value::String(key), Value::Mapping(workspace));
 	}
 
-	println!("{path} - finished capturing workspaces");
+	log_debug!("{path} - finished capturing workspaces");
 
 	Ok(workspaces)
 }
+
+// walks the sub directories down to the configured depth in sorted order, so conflicting names
+// are always resolved the same way
+fn collect_workspaces(
+	dir: &str,
+	relative_dir: &str,
+	depth: u64,
+	options: &AutocaptureOptions,
+	auto_captured_workspaces: &mut Vec<(String, String)>,
+) -> Result<()> {
+	let mut sub_dirs = path_util::get_sub_directories(dir)?
+		.into_iter()
+		.filter(|(key, _)| !key.starts_with('.'))
+		.collect::<Vec<_>>();
+	sub_dirs.sort_unstable();
+
+	// '*' shouldn't cross directories, so 'packages/*' only matches direct children
+	let match_options = MatchOptions {
+		require_literal_separator: true,
+		..MatchOptions::new()
+	};
+
+	let mut nested_dirs = Vec::new();
+
+	for (name, sub_path) in sub_dirs {
+		let relative_path = if relative_dir.is_empty() {
+			name.clone()
+		} else {
+			format!("{relative_dir}/{name}")
+		};
+
+		// excluded directories aren't searched any further either
+		if options
+			.exclude
+			.iter()
+			.any(|pattern| pattern.matches_with(&relative_path, match_options))
+		{
+			continue;
+		}
+
+		let included = options.include.is_empty()
+			|| options
+				.include
+				.iter()
+				.any(|pattern| pattern.matches_with(&relative_path, match_options));
+
+		let has_marker = options.markers.is_empty()
+			|| options
+				.markers
+				.iter()
+				.any(|marker| Utf8Path::new(&sub_path).join(marker).exists());
+
+		if included && has_marker {
+			auto_captured_workspaces.push((
+				if options.path_naming {
+					relative_path.replace('/', "-")
+				} else {
+					name
+				},
+				sub_path.clone(),
+			));
+		}
+
+		nested_dirs.push((sub_path, relative_path));
+	}
+
+	if depth < options.depth {
+		for (sub_path, relative_path) in nested_dirs {
+			collect_workspaces(
+				&sub_path,
+				&relative_path,
+				depth + 1,
+				options,
+				auto_captured_workspaces,
+			)?;
+		}
+	}
+
+	Ok(())
+}
@@ -1,7 +1,69 @@
-pub const SCOPE_RESERVED_KEYS: [&str; 10] =
-	["in", "-in", "variables", "pre", "-pre", "post", "-post", "run", "commands", "tasks"];
+pub const SCOPE_RESERVED_KEYS: [&str; 19] = [
+	"in", "-in", "variables", "options", "pre", "-pre", "post", "-post", "run", "commands", "tasks",
+	"foreach", "-foreach", "needs", "-needs", "sources", "-sources", "outputs", "-outputs",
+];
+
+// the keys of a task collection, whether it's a 'pre', a 'post' or a command entry
+pub const TASK_COLLECTION_KEYS: [&str; 7] =
+	["in", "-in", "tasks", "background", "-background", "parallel", "-parallel"];
+
+// scope keys a command entry can also set, as if it were written as a scope with 'run'
+pub const COMMAND_ENTRY_SCOPE_KEYS: [&str; 9] = [
+	"options", "foreach", "-foreach", "needs", "-needs", "sources", "-sources", "outputs", "-outputs",
+];
+
+pub const OPTION_DECLARATION_KEYS: [&str; 6] =
+	["type", "default", "required", "choices", "description", "positional"];
+
+// flags handled by project-manager itself, which are accepted for every command
+pub const GLOBAL_OPTIONS: [&str; 4] = ["help", "config", "jobs", "j"];
+
+// searched for in the current directory and each of its parents, in order of precedence
+pub const CONFIG_FILE_NAMES: [&str; 3] = ["project.yaml", "project.yml", ".project.yaml"];
+
+pub const CONFIG_ENV_VAR: &str = "PROJECT_MANAGER_CONFIG";
+
+pub const WORKSPACES_RESERVED_KEYS: [&str; 1] = ["autocapture"];
+
+// the mapping form of a workspace, 'name' is only used with a glob 'path'
+pub const WORKSPACE_KEYS: [&str; 6] =
+	["path", "name", "description", "tags", "variables", "depends_on"];
+
+pub const AUTOCAPTURE_KEYS: [&str; 5] = ["depth", "include", "exclude", "markers", "naming"];
+
+// 'leaf' names a workspace after its directory, 'path' after its path from the config directory
+pub const AUTOCAPTURE_NAMING: [&str; 2] = ["leaf", "path"];
+
+// the mapping form of 'foreach', a scope can also just say 'foreach: workspaces' to run in all of them
+pub const FOREACH_KEYS: [&str; 3] = ["filter", "tags", "jobs"];
+
+// flags given to 'each' before the command name, 'j' being short for 'jobs'
+pub const EACH_OPTIONS: [&str; 4] = ["filter", "tag", "jobs", "j"];
+
+// marks a task that runs another command by name, as in '@build'
+pub const TASK_REFERENCE_PREFIX: &str = "@";
 
 pub const STATE_RESERVED_KEYS: [&str; 1] = ["variables"];
 
-// TODO: this should be replaced with the config item in the global config file later on
-pub const SCOPE_SEPARATOR: &str = ":";
+// variable names that are provided by project-manager itself and can't be defined in config
+pub const RESERVED_VARIABLES: [&str; 3] = ["args", "env", "opts"];
+
+pub const ENV_NAMESPACE: &str = "env.";
+
+pub const OPTS_NAMESPACE: &str = "opts.";
+
+// used when the global config file doesn't set 'scope_separator'
+pub const DEFAULT_SCOPE_SEPARATOR: &str = ":";
+
+// prefixes and shorthand markers the desugarer looks for in scope keys
+pub const SCOPE_KEY_RESERVED_CHARS: [char; 3] = ['?', '!', '$'];
+
+pub const GLOBAL_CONFIG_ROOT_KEYS: [&str; 2] = ["settings", "commands"];
+
+pub const SETTINGS_KEYS: [&str; 4] = ["scope_separator", "shell", "color", "log_level"];
+
+// kept next to the project config, holding what project-manager remembers between runs
+pub const STATE_DIR: &str = ".project-manager";
+
+// resolved relative to $XDG_CONFIG_HOME, or ~/.config when it isn't set
+pub const GLOBAL_CONFIG_PATH: &str = "project-manager/config.yaml";
@@ -1,14 +1,14 @@
 use color_eyre::{Result, eyre::eyre};
 use serde_yaml::{Mapping, Value};
 
-use crate::{config::expand, util::yaml};
+use crate::{config::expand, log_debug, util::yaml};
 
 pub fn desugar_mapping<'a>(
 	path: &str,
 	config_dir: &str,
 	mapping: &'a mut Mapping,
 ) -> Result<&'a mut Mapping> {
-	println!("{path} - desugaring mapping");
+	log_debug!("{path} - desugaring mapping");
 
 	let keys: Vec<Value> = mapping.keys().cloned().collect();
 	for key in keys {
@@ -20,7 +20,7 @@ pub fn desugar_mapping<'a>(
 
 		let key = key.as_str().unwrap();
 
-		println!("{path} - desugaring '{key}'");
+		log_debug!("{path} - desugaring '{key}'");
 
 		let (base_key, mut shorthand_props) = normalize_key(key)?;
 		if !shorthand_props.is_empty() {
@@ -103,7 +103,7 @@ pub fn desugar_mapping<'a>(
 		}
 	}
 
-	println!("{path} - mapping desugared");
+	log_debug!("{path} - mapping desugared");
 
 	Ok(mapping)
 }
@@ -0,0 +1,108 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::Mapping;
+
+use crate::{config::constants, util::yaml};
+
+// finds the project config to load, in order of precedence: the `--config` flag, the
+// `PROJECT_MANAGER_CONFIG` env var, then searching upwards from the current directory. from inside
+// a workspace, that's the outermost config defining workspaces rather than the workspace's own
+pub fn find_project_config(config_flag: Option<&str>) -> Result<String> {
+	let current_dir = Utf8PathBuf::from_path_buf(std::env::current_dir()?)
+		.map_err(|_| eyre!("unable to process non UTF-8 path"))?;
+
+	if let Some(config_path) = config_flag {
+		return get_explicit_config(&current_dir, config_path, "--config");
+	}
+
+	if let Ok(config_path) = std::env::var(constants::CONFIG_ENV_VAR)
+		&& !config_path.is_empty()
+	{
+		return get_explicit_config(&current_dir, &config_path, constants::CONFIG_ENV_VAR);
+	}
+
+	let mut tried = Vec::new();
+	let mut found = None;
+
+	for dir in current_dir.ancestors() {
+		for file_name in constants::CONFIG_FILE_NAMES {
+			let candidate = dir.join(file_name);
+			if candidate.is_file() {
+				// the config of a workspace is mounted by the config defining the workspaces, so
+				// the search goes on past it
+				if found.is_none() || defines_workspaces(&candidate)? {
+					found = Some(candidate);
+				}
+
+				break;
+			}
+
+			tried.push(candidate);
+		}
+
+		// the root of a repository is as far as a project config can be
+		if dir.join(".git").exists() {
+			break;
+		}
+	}
+
+	if let Some(config_path) = found {
+		return Ok(config_path.into_string());
+	}
+
+	Err(eyre!(
+		"unable to find a project config file. tried:\n{}\nuse '--config <path>' or set {} to load a config from elsewhere",
+		tried
+			.iter()
+			.map(|path| format!("- {path}"))
+			.collect::<Vec<_>>()
+			.join("\n"),
+		constants::CONFIG_ENV_VAR
+	))
+}
+
+fn defines_workspaces(config_path: &Utf8Path) -> Result<bool> {
+	let config: Mapping = yaml::load_yaml(config_path.as_str())?;
+
+	Ok(config.contains_key("workspaces"))
+}
+
+fn get_explicit_config(current_dir: &Utf8Path, config_path: &str, source: &str) -> Result<String> {
+	let config_path = current_dir.join(config_path);
+
+	if !config_path.is_file() {
+		return Err(eyre!(
+			"unable to find config file '{config_path}' given by {source}. tried:\n- {config_path}"
+		));
+	}
+
+	Ok(config_path.into_string())
+}
+
+// the global config is optional, so a missing file isn't an error
+pub fn find_global_config() -> Result<Option<String>> {
+	let config_dir = match std::env::var("XDG_CONFIG_HOME") {
+		Ok(config_home) if !config_home.is_empty() => Utf8PathBuf::from(config_home),
+		_ => {
+			let Some(home) = home::home_dir() else {
+				return Ok(None);
+			};
+
+			Utf8PathBuf::from_path_buf(home)
+				.map_err(|_| eyre!("home directory is not valid UTF-8"))?
+				.join(".config")
+		}
+	};
+
+	let config_path = config_dir.join(constants::GLOBAL_CONFIG_PATH);
+
+	Ok(config_path.is_file().then(|| config_path.into_string()))
+}
+
+// the state directory sits next to the project config, at the root of the project
+pub fn get_state_dir(project_config_path: &str) -> Utf8PathBuf {
+	Utf8Path::new(project_config_path)
+		.parent()
+		.unwrap_or(Utf8Path::new("."))
+		.join(constants::STATE_DIR)
+}
@@ -1,17 +1,16 @@
-use camino::Utf8PathBuf;
-use color_eyre::{Result, eyre::eyre};
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{Report, Result, eyre::eyre};
 use serde_yaml::{Mapping, Value};
 
 use crate::{
-	config::{constants, desugar},
-	util::{path as path_util, yaml},
+	config::{constants, desugar, names},
+	log_debug,
+	models::options::OptionType,
+	util::{path as path_util, template, yaml},
 };
 
 // TODO:
 // - need to auto capture workspaces from root directory
-// - need to build reserved variables
-//   - $env built from current environment variables
-//   - $opts built from command line arguments
 
 pub fn expand_project_config<'a>(
 	config_dir: &str,
@@ -29,6 +28,8 @@ pub fn expand_project_config<'a>(
 	yaml::map_mapping(config, |key: &str, value: &mut Value| {
 		if let Some(value_mapping) = value.as_mapping_mut() {
 			match key {
+				// settings are read before expanding, as they apply to the whole config
+				"settings" => Ok(()),
 				"workspaces" => {
 					expand_workspaces("workspaces", config_dir, value_mapping)?;
 
@@ -54,7 +55,28 @@ pub fn expand_project_config<'a>(
 	Ok(config)
 }
 
-pub fn expand_internal_config(config: &mut Mapping) -> Result<&mut Mapping> {
+// the global config only holds settings and personal commands, settings are read before expanding
+pub fn expand_internal_config<'a>(
+	config_dir: &str,
+	config: &'a mut Mapping,
+) -> Result<&'a mut Mapping> {
+	yaml::map_mapping(config, |key: &str, value: &mut Value| match key {
+		"settings" => Ok(()),
+		"commands" => {
+			let value_mapping = value.as_mapping_mut().ok_or_else(|| {
+				eyre!("key value is invalid type in mapping: {key:#?}\nkey value must be a mapping")
+			})?;
+
+			expand_scope("commands", config_dir, value_mapping, true)?;
+
+			Ok(())
+		}
+		_ => Err(eyre!(
+			"unable to process unknown key: {key}\nexpected one of: {}",
+			constants::GLOBAL_CONFIG_ROOT_KEYS.join(", ")
+		)),
+	})?;
+
 	Ok(config)
 }
 
@@ -63,21 +85,50 @@ pub fn expand_workspaces<'a>(
 	config_dir: &str,
 	workspaces: &'a mut Mapping,
 ) -> Result<&'a mut Mapping> {
-	println!("{path} - expanding workspaces");
+	log_debug!("{path} - expanding workspaces");
+
+	let mut glob_workspaces = Vec::new();
 
 	yaml::map_mapping(workspaces, |key, value| {
-		println!("{path} - processing '{key}' (workspace): {value:?}");
+		if constants::WORKSPACES_RESERVED_KEYS.contains(&key) {
+			log_debug!("{path} - processing '{key}' (autocapture): {value:?}");
+
+			expand_autocapture(format!("{path}.{key}").as_str(), value)?;
+
+			return Ok(());
+		}
+
+		log_debug!("{path} - processing '{key}' (workspace): {value:?}");
+
+		let (value_str, name_template, details) = match value {
+			Value::String(value_str) => (value_str.clone(), None, Mapping::new()),
+			Value::Mapping(value_mapping) => get_workspace_definition(path, key, value_mapping)?,
+			_ => {
+				return Err(eyre!(
+					"key value is invalid type in mapping: {key:#?}\nkey value must be a string or a mapping"
+				));
+			}
+		};
+
+		if path_util::is_glob(&value_str) {
+			glob_workspaces.push((key.to_string(), value_str, name_template, details));
 
-		if !value.is_string() {
+			return Ok(());
+		}
+
+		if name_template.is_some() {
 			return Err(eyre!(
-				"key value is invalid type in mapping: {key:#?}\nkey value must be a string"
+				"invalid workspace '{path}.{key}'\n'name' can only be used when 'path' is a glob"
 			));
 		}
 
-		let value_str = value.as_str().unwrap();
+		if let Some(workspace_path) =
+			path_util::try_get_path(&value_str, Some(config_dir.to_string()))?
+		{
+			let mut path_value = Value::Null;
+			process_path(&mut path_value, workspace_path)?;
 
-		if let Some(path) = path_util::try_get_path(value_str, Some(config_dir.to_string()))? {
-			process_path(value, path)?;
+			*value = get_workspace_mapping(path_value, &details);
 
 			Ok(())
 		} else {
@@ -85,13 +136,250 @@ pub fn expand_workspaces<'a>(
 		}
 	})?;
 
-	println!("{path} - workspaces expanded");
+	// a glob is replaced by a workspace for every directory it matches, which all share its details
+	for (key, pattern, name_template, details) in glob_workspaces {
+		workspaces.shift_remove(&key);
+
+		for (name, dir) in
+			expand_workspace_glob(path, config_dir, &key, &pattern, name_template.as_deref())?
+		{
+			if workspaces.contains_key(&name) {
+				return Err(eyre!(
+					"conflicting workspace '{name}' ({dir}) matched by '{path}.{key}' ({pattern})\na workspace with that name is already defined"
+				));
+			}
+
+			log_debug!("{path} - '{key}' matched '{name}' ({dir})");
+			workspaces.insert(
+				Value::String(name),
+				get_workspace_mapping(Value::String(dir), &details),
+			);
+		}
+	}
+
+	log_debug!("{path} - workspaces expanded");
 
 	Ok(workspaces)
 }
 
+// workspaces are normalized to mappings, so the plain string form is the same as only giving a 'path'
+fn get_workspace_mapping(path: Value, details: &Mapping) -> Value {
+	let mut workspace = Mapping::new();
+	workspace.insert(Value::String("path".to_string()), path);
+	workspace.extend(details.clone());
+
+	Value::Mapping(workspace)
+}
+
+// splits a workspace mapping into its path, its name template (only for globs) and the details
+// every workspace it defines gets
+fn get_workspace_definition(
+	path: &str,
+	key: &str,
+	workspace: &mut Mapping,
+) -> Result<(String, Option<String>, Mapping)> {
+	let mut details = Mapping::new();
+	let mut workspace_path = None;
+	let mut name_template = None;
+
+	for (option_key, value) in workspace.iter_mut() {
+		let option_key = option_key.as_str().unwrap_or_default();
+
+		match option_key {
+			"path" => {
+				workspace_path = Some(value.as_str().ok_or_else(|| {
+					eyre!("invalid value for '{path}.{key}.path': {value:?}\npath must be a string")
+				})?);
+			}
+			"name" => {
+				name_template = Some(value.as_str().ok_or_else(|| {
+					eyre!(
+						"invalid value for '{path}.{key}.name': {value:?}\nname must be a string template"
+					)
+				})?);
+			}
+			"description" => {
+				if !value.is_string() {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}.description': {value:?}\ndescription must be a string"
+					));
+				}
+
+				details.insert(option_key.into(), value.clone());
+			}
+			"tags" | "depends_on" => {
+				if value.is_string() {
+					*value = Value::Sequence(vec![value.clone()]);
+				}
+
+				if !value
+					.as_sequence()
+					.is_some_and(|items| items.iter().all(Value::is_string))
+				{
+					return Err(eyre!(
+						"invalid value for '{path}.{key}.{option_key}': {value:?}\n{option_key} must be a string or list of strings"
+					));
+				}
+
+				details.insert(option_key.into(), value.clone());
+			}
+			"variables" => {
+				let Some(variables) = value.as_mapping_mut() else {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}.variables': {value:?}\nvariables must be a mapping"
+					));
+				};
+
+				expand_variables(format!("{path}.{key}.variables").as_str(), variables)?;
+
+				details.insert(option_key.into(), value.clone());
+			}
+			_ => {
+				return Err(eyre!(
+					"unknown key in workspace '{path}.{key}': '{option_key}'\nexpected one of: {}",
+					constants::WORKSPACE_KEYS.join(", ")
+				));
+			}
+		}
+	}
+
+	let Some(workspace_path) = workspace_path else {
+		return Err(eyre!(
+			"invalid workspace '{path}.{key}'\n'path' is required and must be a string"
+		));
+	};
+
+	Ok((
+		workspace_path.to_string(),
+		name_template.map(str::to_string),
+		details,
+	))
+}
+
+// finds every directory a workspace glob matches, in sorted order. workspaces are named after their
+// directory, unless a template is given which can use '{{name}}' (the directory) and '{{key}}'
+fn expand_workspace_glob(
+	path: &str,
+	config_dir: &str,
+	key: &str,
+	pattern: &str,
+	name_template: Option<&str>,
+) -> Result<Vec<(String, String)>> {
+	let full_pattern = Utf8Path::new(config_dir).join(pattern);
+
+	let mut dirs = glob::glob(full_pattern.as_str())
+		.map_err(|err| eyre!("invalid glob for workspace '{path}.{key}': '{pattern}'\n{err}"))?
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(|err| eyre!("unable to expand workspace '{path}.{key}': '{pattern}'\n{err}"))?;
+	dirs.retain(|dir| dir.is_dir());
+	dirs.sort();
+
+	let mut matched_workspaces = Vec::new();
+
+	for dir in dirs {
+		let dir = Utf8PathBuf::from_path_buf(dunce::canonicalize(dir)?)
+			.map_err(|_| eyre!("unable to process non UTF-8 path"))?;
+
+		let dir_name = dir.file_name().unwrap_or_default().replace(' ', "-");
+
+		let name = if let Some(name_template) = name_template {
+			template::render(name_template, |expression| match expression {
+				"name" => Ok(dir_name.clone()),
+				"key" => Ok(key.to_string()),
+				_ => Err(eyre!(
+					"unknown placeholder '{{{{{expression}}}}}' in name of workspace '{path}.{key}'\nexpected one of: {{{{name}}}}, {{{{key}}}}"
+				)),
+			})?
+		} else {
+			dir_name
+		};
+
+		matched_workspaces.push((name, dir.into_string()));
+	}
+
+	Ok(matched_workspaces)
+}
+
+// normalizes the autocapture options, so 'true' becomes the default options and every pattern list is a sequence
+pub fn expand_autocapture<'a>(path: &str, autocapture: &'a mut Value) -> Result<&'a mut Value> {
+	log_debug!("{path} - expanding autocapture");
+
+	let options = match autocapture {
+		Value::Bool(false) => return Ok(autocapture),
+		Value::Bool(true) => {
+			*autocapture = Value::Mapping(Mapping::new());
+			autocapture.as_mapping_mut().unwrap()
+		}
+		Value::Mapping(options) => options,
+		_ => {
+			return Err(eyre!(
+				"key value is invalid type in mapping: {path}\nkey value must be a bool or a mapping of autocapture options"
+			));
+		}
+	};
+
+	yaml::map_mapping(options, |key, value| {
+		log_debug!("{path} - processing '{key}' (autocapture option): {value:?}");
+
+		match key {
+			"depth" => {
+				if value.as_u64().is_none_or(|depth| depth == 0) {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\ndepth must be a positive integer"
+					));
+				}
+			}
+			"include" | "exclude" | "markers" => {
+				if value.is_string() {
+					*value = Value::Sequence(vec![value.clone()]);
+				}
+
+				let Some(items) = value
+					.as_sequence()
+					.filter(|items| items.iter().all(Value::is_string))
+				else {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\nvalue must be a string or list of strings"
+					));
+				};
+
+				if key != "markers" {
+					for pattern in items.iter().map(|item| item.as_str().unwrap()) {
+						glob::Pattern::new(pattern).map_err(|err| {
+							eyre!("invalid glob pattern in '{path}.{key}': '{pattern}'\n{err}")
+						})?;
+					}
+				}
+			}
+			"naming" => {
+				if !value
+					.as_str()
+					.is_some_and(|naming| constants::AUTOCAPTURE_NAMING.contains(&naming))
+				{
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\nexpected one of: {}",
+						constants::AUTOCAPTURE_NAMING.join(", ")
+					));
+				}
+			}
+			_ => {
+				return Err(eyre!(
+					"unknown autocapture option: {path}.{key}\nexpected one of: {}",
+					constants::AUTOCAPTURE_KEYS.join(", ")
+				));
+			}
+		}
+
+		Ok(())
+	})?;
+
+	log_debug!("{path} - autocapture expanded");
+
+	Ok(autocapture)
+}
+
 pub fn expand_state<'a>(path: &str, state: &'a mut Mapping) -> Result<&'a mut Mapping> {
-	println!("{path} - expanding state");
+	log_debug!("{path} - expanding state");
 
 	let shorthand_variable_keys = get_shorthand_variable_keys(state);
 	if !shorthand_variable_keys.is_empty() {
@@ -100,7 +388,7 @@ pub fn expand_state<'a>(path: &str, state: &'a mut Mapping) -> Result<&'a mut Ma
 
 	yaml::map_mapping(state, |key, value| match desugar::get_base_key(key, true) {
 		"variables" => {
-			println!("{path} - processing '{key}' (variables): {value:?}");
+			log_debug!("{path} - processing '{key}' (variables): {value:?}");
 
 			expand_variables(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
@@ -110,20 +398,20 @@ pub fn expand_state<'a>(path: &str, state: &'a mut Mapping) -> Result<&'a mut Ma
 			Ok(())
 		}
 		_ if constants::STATE_RESERVED_KEYS.contains(&key) => {
-			println!("{path} - processing '{key}' (unhandled reserved): {value:?}");
+			log_debug!("{path} - processing '{key}' (unhandled reserved): {value:?}");
 
 			Err(eyre!(
 				"processing a reserved key that should have been explicitly handled: {path}.{key}"
 			))
 		}
 		_ => {
-			println!("{path} - processing '{key}' (unknown): {value:?}");
+			log_debug!("{path} - processing '{key}' (unknown): {value:?}");
 
 			Err(eyre!("unable to process unknown key: {path}.{key}"))
 		}
 	})?;
 
-	println!("{path} - state expanded");
+	log_debug!("{path} - state expanded");
 
 	Ok(state)
 }
@@ -134,7 +422,7 @@ pub fn expand_scope<'a>(
 	scope: &'a mut Mapping,
 	strict: bool,
 ) -> Result<&'a mut Mapping> {
-	println!("{path} - expanding scope");
+	log_debug!("{path} - expanding scope");
 
 	// process shorthand variables if any exist
 	let shorthand_variable_keys = get_shorthand_variable_keys(scope);
@@ -155,7 +443,7 @@ pub fn expand_scope<'a>(
 
 	yaml::map_mapping(scope, |key, value| match desugar::get_base_key(key, true) {
 		"variables" => {
-			println!("{path} - processing '{key}' (variables): {value:?}");
+			log_debug!("{path} - processing '{key}' (variables): {value:?}");
 
 			expand_variables(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
@@ -164,28 +452,46 @@ pub fn expand_scope<'a>(
 
 			Ok(())
 		}
+		"options" => {
+			log_debug!("{path} - processing '{key}' (options): {value:?}");
+
+			if !value.is_mapping() {
+				return Err(eyre!(
+					"key value is invalid type in mapping: {path}.{key}\nkey value must be a mapping of option declarations"
+				));
+			}
+
+			expand_options(
+				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
+				value.as_mapping_mut().unwrap(),
+			)?;
+
+			Ok(())
+		}
 		"pre" => {
-			println!("{path} - processing '{key}' (pre): {value:?}");
+			log_debug!("{path} - processing '{key}' (pre): {value:?}");
 
 			expand_task_collection(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
 				value,
+				&[],
 			)?;
 
 			Ok(())
 		}
 		"post" => {
-			println!("{path} - processing '{key}' (post): {value:?}");
+			log_debug!("{path} - processing '{key}' (post): {value:?}");
 
 			expand_task_collection(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
 				value,
+				&[],
 			)?;
 
 			Ok(())
 		}
 		"commands" => {
-			println!("{path} - processing '{key}' (commands): {value:?}");
+			log_debug!("{path} - processing '{key}' (commands): {value:?}");
 
 			expand_commands(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
@@ -195,22 +501,43 @@ pub fn expand_scope<'a>(
 			Ok(())
 		}
 		"in" => {
-			println!("{path} - processing '{key}' (in): {value:?}");
+			log_debug!("{path} - processing '{key}' (in): {value:?}");
 
 			expand_potential_path(format!("{path}.{key}").as_str(), config_dir, value)?;
 
 			Ok(())
 		}
-		"-in" | "-pre" | "-post" => Ok(()),
+		"foreach" => {
+			log_debug!("{path} - processing '{key}' (foreach): {value:?}");
+
+			expand_foreach(format!("{path}.{key}").as_str(), value)?;
+
+			Ok(())
+		}
+		"needs" => {
+			log_debug!("{path} - processing '{key}' (needs): {value:?}");
+
+			expand_needs(format!("{path}.{key}").as_str(), value)?;
+
+			Ok(())
+		}
+		"sources" | "outputs" => {
+			log_debug!("{path} - processing '{key}' ({key}): {value:?}");
+
+			expand_paths(format!("{path}.{key}").as_str(), value)?;
+
+			Ok(())
+		}
+		"-in" | "-pre" | "-post" | "-foreach" | "-needs" | "-sources" | "-outputs" => Ok(()),
 		_ if constants::SCOPE_RESERVED_KEYS.contains(&key) => {
-			println!("{path} - processing '{key}' (unhandled reserved): {value:?}");
+			log_debug!("{path} - processing '{key}' (unhandled reserved): {value:?}");
 
 			Err(eyre!(
 				"processing a reserved key that should have been explicitly handled: {path}.{key}"
 			))
 		}
 		_ if value.is_mapping() => {
-			println!("{path} - processing '{key}' (sub scope): {value:?}");
+			log_debug!("{path} - processing '{key}' (sub scope): {value:?}");
 
 			expand_scope(
 				format!("{path}.{}", desugar::get_base_key(key, true)).as_str(),
@@ -222,7 +549,7 @@ pub fn expand_scope<'a>(
 			Ok(())
 		}
 		_ => {
-			println!("{path} - processing '{key}' (unknown): {value:?}");
+			log_debug!("{path} - processing '{key}' (unknown): {value:?}");
 
 			if strict {
 				Err(eyre!("unable to process unknown key: {path}.{key}"))
@@ -232,7 +559,7 @@ pub fn expand_scope<'a>(
 		}
 	})?;
 
-	println!("{path} - scope expanded");
+	log_debug!("{path} - scope expanded");
 
 	Ok(scope)
 }
@@ -266,7 +593,7 @@ pub fn expand_shorthand_variables<'a>(
 	for var_key in shorthand_variable_keys {
 		let var_key_value = scope.remove(format!("${}", var_key)).unwrap();
 
-		println!("{scope_path} - processing '${var_key}' (variable): {var_key_value:?}");
+		log_debug!("{scope_path} - processing '${var_key}' (variable): {var_key_value:?}");
 
 		scope["variables"]
 			.as_mapping_mut()
@@ -278,7 +605,7 @@ pub fn expand_shorthand_variables<'a>(
 }
 
 pub fn expand_variables<'a>(path: &str, variables: &'a mut Mapping) -> Result<&'a mut Mapping> {
-	println!("{path} - expanding variables");
+	log_debug!("{path} - expanding variables");
 
 	let shorthand_variable_keys = get_shorthand_variable_keys(variables);
 	if !shorthand_variable_keys.is_empty() {
@@ -290,7 +617,14 @@ pub fn expand_variables<'a>(path: &str, variables: &'a mut Mapping) -> Result<&'
 	}
 
 	yaml::map_mapping(variables, |key, value| {
-		println!("{path} - processing '{key}' (variable): {value:?}");
+		log_debug!("{path} - processing '{key}' (variable): {value:?}");
+
+		if constants::RESERVED_VARIABLES.contains(&key) || key.contains('.') {
+			return Err(eyre!(
+				"invalid variable name: {path}.{key}\nvariable name must not contain '.' or be one of: {}",
+				constants::RESERVED_VARIABLES.join(", ")
+			));
+		}
 
 		let mut var_mapping = Mapping::new();
 		var_mapping.insert(Value::String("value".to_string()), value.clone());
@@ -300,11 +634,284 @@ pub fn expand_variables<'a>(path: &str, variables: &'a mut Mapping) -> Result<&'
 		Ok(())
 	})?;
 
-	println!("{path} - variables expanded");
+	log_debug!("{path} - variables expanded");
 
 	Ok(variables)
 }
 
+pub fn expand_options<'a>(path: &str, options: &'a mut Mapping) -> Result<&'a mut Mapping> {
+	log_debug!("{path} - expanding options");
+
+	let mut positional_optional = false;
+
+	yaml::map_mapping(options, |key, value| {
+		log_debug!("{path} - processing '{key}' (option): {value:?}");
+
+		if value.is_null() {
+			*value = Value::Mapping(Mapping::new());
+		}
+
+		let Some(declaration) = value.as_mapping_mut() else {
+			return Err(eyre!(
+				"key value is invalid type in mapping: {path}.{key}\nkey value must be a mapping or null"
+			));
+		};
+
+		for declaration_key in declaration.keys() {
+			let declaration_key = declaration_key.as_str().unwrap_or_default();
+			if !constants::OPTION_DECLARATION_KEYS.contains(&declaration_key) {
+				return Err(eyre!(
+					"unable to process unknown key: {path}.{key}.{declaration_key}\noption declarations may only contain: {}",
+					constants::OPTION_DECLARATION_KEYS.join(", ")
+				));
+			}
+		}
+
+		let option_type = match declaration.get("type") {
+			None => OptionType::String,
+			Some(Value::String(type_name)) => {
+				OptionType::from_name(type_name).ok_or_else(|| {
+					eyre!(
+						"invalid option type: {path}.{key}.type\ntype must be one of: string, number, bool"
+					)
+				})?
+			}
+			Some(_) => {
+				return Err(eyre!(
+					"invalid option type: {path}.{key}.type\ntype must be a string"
+				));
+			}
+		};
+
+		for flag_key in ["required", "positional"] {
+			match declaration.get(flag_key) {
+				None => {
+					declaration.insert(Value::String(flag_key.to_string()), Value::Bool(false));
+				}
+				Some(Value::Bool(_)) => {}
+				Some(_) => {
+					return Err(eyre!(
+						"key value is invalid type in mapping: {path}.{key}.{flag_key}\nkey value must be a bool"
+					));
+				}
+			}
+		}
+
+		let positional = declaration["positional"].as_bool().unwrap();
+		let required = declaration["required"].as_bool().unwrap();
+
+		if positional && option_type == OptionType::Bool {
+			return Err(eyre!(
+				"invalid option declaration: {path}.{key}\npositional arguments can't be of type bool"
+			));
+		}
+
+		// a required positional after an optional one could never be given on its own
+		if positional {
+			if required && positional_optional {
+				return Err(eyre!(
+					"invalid option declaration: {path}.{key}\nrequired positional arguments must come before optional ones"
+				));
+			}
+
+			positional_optional |= !required;
+		}
+
+		if let Some(description) = declaration.get("description")
+			&& !description.is_string()
+		{
+			return Err(eyre!(
+				"key value is invalid type in mapping: {path}.{key}.description\nkey value must be a string"
+			));
+		}
+
+		let choices = match declaration.get("choices") {
+			None => Vec::new(),
+			Some(Value::Sequence(choices)) => choices
+				.iter()
+				.map(|choice| match choice {
+					Value::String(choice) => Ok(choice.clone()),
+					Value::Number(choice) => Ok(choice.to_string()),
+					_ => Err(eyre!(
+						"invalid option choice: {path}.{key}.choices: {choice:?}\nchoices must be strings or numbers"
+					)),
+				})
+				.collect::<Result<Vec<_>>>()?,
+			Some(_) => {
+				return Err(eyre!(
+					"key value is invalid type in mapping: {path}.{key}.choices\nkey value must be a sequence"
+				));
+			}
+		};
+
+		if let Some(default) = declaration.get("default") {
+			let valid = matches!(
+				(option_type, default),
+				(OptionType::String, Value::String(_))
+					| (OptionType::Number, Value::Number(_))
+					| (OptionType::Bool, Value::Bool(_))
+			);
+
+			if !valid {
+				return Err(eyre!(
+					"invalid option default: {path}.{key}.default: {default:?}\ndefault must be a {option_type}"
+				));
+			}
+		}
+
+		declaration.insert(
+			Value::String("type".to_string()),
+			Value::String(option_type.to_string()),
+		);
+		declaration.insert(
+			Value::String("choices".to_string()),
+			Value::Sequence(choices.into_iter().map(Value::String).collect()),
+		);
+
+		Ok(())
+	})?;
+
+	log_debug!("{path} - options expanded");
+
+	Ok(options)
+}
+
+// normalizes 'foreach' into a mapping of workspace filters, where 'workspaces' selects all of them
+pub fn expand_foreach<'a>(path: &str, foreach: &'a mut Value) -> Result<&'a mut Value> {
+	log_debug!("{path} - expanding foreach");
+
+	let selection = match foreach {
+		Value::String(target) if target == "workspaces" => {
+			*foreach = Value::Mapping(Mapping::new());
+			foreach.as_mapping_mut().unwrap()
+		}
+		Value::Mapping(selection) => selection,
+		_ => {
+			return Err(eyre!(
+				"key value is invalid type in mapping: {path}\nkey value must be 'workspaces' or a mapping of workspace filters"
+			));
+		}
+	};
+
+	yaml::map_mapping(selection, |key, value| {
+		log_debug!("{path} - processing '{key}' (foreach option): {value:?}");
+
+		match key {
+			"filter" => {
+				let Some(pattern) = value.as_str() else {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\nfilter must be a glob matching workspace names"
+					));
+				};
+
+				glob::Pattern::new(pattern).map_err(|err| {
+					eyre!("invalid glob pattern in '{path}.{key}': '{pattern}'\n{err}")
+				})?;
+			}
+			"tags" => {
+				if value.is_string() {
+					*value = Value::Sequence(vec![value.clone()]);
+				}
+
+				if !value
+					.as_sequence()
+					.is_some_and(|tags| tags.iter().all(Value::is_string))
+				{
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\nvalue must be a string or list of strings"
+					));
+				}
+			}
+			"jobs" => {
+				if value.as_u64().is_none_or(|jobs| jobs == 0) {
+					return Err(eyre!(
+						"invalid value for '{path}.{key}': {value:?}\njobs must be a positive integer"
+					));
+				}
+			}
+			_ => {
+				return Err(eyre!(
+					"unknown foreach option: {path}.{key}\nexpected one of: {}",
+					constants::FOREACH_KEYS.join(", ")
+				));
+			}
+		}
+
+		Ok(())
+	})?;
+
+	log_debug!("{path} - foreach expanded");
+
+	Ok(foreach)
+}
+
+// normalizes 'needs' into a list of command names
+pub fn expand_needs<'a>(path: &str, needs: &'a mut Value) -> Result<&'a mut Value> {
+	log_debug!("{path} - expanding needs");
+
+	if needs.is_string() {
+		*needs = Value::Sequence(vec![needs.clone()]);
+	}
+
+	let Some(commands) = needs.as_sequence() else {
+		return Err(eyre!(
+			"key value is invalid type in mapping: {path}\nkey value must be a command name or a list of them"
+		));
+	};
+
+	for command in commands {
+		if !command.as_str().is_some_and(|command| {
+			!command.is_empty()
+				&& !command.contains(char::is_whitespace)
+				&& !template::has_placeholders(command)
+		}) {
+			return Err(eyre!(
+				"invalid command in '{path}': {command:?}\na needed command must be the name of a command, without spaces or placeholders"
+			));
+		}
+	}
+
+	log_debug!("{path} - needs expanded");
+
+	Ok(needs)
+}
+
+// normalizes 'sources' and 'outputs' into a list of paths or globs, which are relative to the
+// directory the command runs in
+pub fn expand_paths<'a>(path: &str, paths: &'a mut Value) -> Result<&'a mut Value> {
+	log_debug!("{path} - expanding paths");
+
+	if paths.is_string() {
+		*paths = Value::Sequence(vec![paths.clone()]);
+	}
+
+	if !paths
+		.as_sequence()
+		.is_some_and(|items| items.iter().all(Value::is_string))
+	{
+		return Err(eyre!(
+			"key value is invalid type in mapping: {path}\nkey value must be a path, a glob or a list of them"
+		));
+	}
+
+	for pattern in paths
+		.as_sequence()
+		.unwrap()
+		.iter()
+		.map(|item| item.as_str().unwrap())
+	{
+		// templated patterns are only checked once they've been interpolated
+		if !template::has_placeholders(pattern) {
+			glob::Pattern::new(pattern)
+				.map_err(|err| eyre!("invalid glob pattern in '{path}': '{pattern}'\n{err}"))?;
+		}
+	}
+
+	log_debug!("{path} - paths expanded");
+
+	Ok(paths)
+}
+
 pub fn has_key(scope: &mut Mapping, key: &str) -> bool {
 	scope.iter().any(|(k, _)| {
 		if let Some(k) = k.as_str() {
@@ -329,7 +936,7 @@ pub fn expand_run<'a>(
 
 	let run_val = scope.remove(run_key).unwrap();
 
-	println!("{scope_path} - processing '{run_key}' (run): {run_val:?}");
+	log_debug!("{scope_path} - processing '{run_key}' (run): {run_val:?}");
 
 	scope["commands"].as_mapping_mut().unwrap().insert(
 		Value::String(run_key.replace("run", ".").to_string()),
@@ -371,7 +978,7 @@ pub fn expand_implicit_commands<'a>(
 	for implicit_command_key in implicit_command_keys {
 		let implicit_command_key_value = scope.remove(implicit_command_key.clone()).unwrap();
 
-		println!(
+		log_debug!(
 			"{scope_path} - processing '{implicit_command_key}' (implicit command): {implicit_command_key_value:?}",
 		);
 
@@ -385,10 +992,10 @@ pub fn expand_implicit_commands<'a>(
 }
 
 pub fn expand_commands<'a>(path: &str, commands: &'a mut Mapping) -> Result<&'a mut Mapping> {
-	println!("{path} - expanding commands");
+	log_debug!("{path} - expanding commands");
 
 	yaml::map_mapping(commands, |key, value| {
-		println!("{path} - processing '{key}' (command): {value:?}");
+		log_debug!("{path} - processing '{key}' (command): {value:?}");
 
 		expand_task_collection(
 			format!(
@@ -401,23 +1008,29 @@ pub fn expand_commands<'a>(path: &str, commands: &'a mut Mapping) -> Result<&'a
 			)
 			.as_str(),
 			value,
+			&constants::COMMAND_ENTRY_SCOPE_KEYS,
 		)?;
 
 		Ok(())
 	})?;
 
-	println!("{path} - commands expanded");
+	log_debug!("{path} - commands expanded");
 
 	Ok(commands)
 }
 
+// `scope_keys` are the scope keys the task collection can set besides its own, which only a command
+// entry has
 pub fn expand_task_collection<'a>(
 	path: &str,
 	implicit_task_collection: &'a mut Value,
+	scope_keys: &[&str],
 ) -> Result<&'a mut Value> {
-	println!("{path} - expanding task collection");
+	log_debug!("{path} - expanding task collection");
 
-	if !implicit_task_collection.is_mapping() {
+	// a lone '{ run: <command> }' is a task, rather than a task collection mapping
+	if !implicit_task_collection.is_mapping() || is_task_reference_mapping(implicit_task_collection)
+	{
 		let mut tasks_mapping = Mapping::new();
 		tasks_mapping.insert(
 			Value::String("tasks".to_string()),
@@ -427,9 +1040,59 @@ pub fn expand_task_collection<'a>(
 		*implicit_task_collection = Value::Mapping(tasks_mapping);
 	}
 
-	let task_collection = implicit_task_collection.get_mut("tasks").unwrap();
+	// anything else would be silently ignored when the command runs
+	yaml::map_mapping(
+		implicit_task_collection.as_mapping_mut().unwrap(),
+		|key, value| match key {
+			_ if constants::TASK_COLLECTION_KEYS.contains(&key) => Ok(()),
+			"options" if scope_keys.contains(&key) => {
+				let Some(options) = value.as_mapping_mut() else {
+					return Err(eyre!(
+						"key value is invalid type in mapping: {path}.{key}\nkey value must be a mapping of option declarations"
+					));
+				};
+
+				expand_options(format!("{path}.{key}").as_str(), options)?;
+
+				Ok(())
+			}
+			"foreach" if scope_keys.contains(&key) => {
+				expand_foreach(format!("{path}.{key}").as_str(), value)?;
+
+				Ok(())
+			}
+			"needs" if scope_keys.contains(&key) => {
+				expand_needs(format!("{path}.{key}").as_str(), value)?;
 
-	if task_collection.is_string() {
+				Ok(())
+			}
+			"sources" | "outputs" if scope_keys.contains(&key) => {
+				expand_paths(format!("{path}.{key}").as_str(), value)?;
+
+				Ok(())
+			}
+			_ if scope_keys.contains(&key) => Ok(()),
+			_ => Err(eyre!(
+				"unable to process unknown key: {path}.{key}
+expected one of: {}",
+				constants::TASK_COLLECTION_KEYS
+					.iter()
+					.chain(scope_keys)
+					.filter(|key| !key.starts_with('-'))
+					.copied()
+					.collect::<Vec<_>>()
+					.join(", ")
+			)),
+		},
+	)?;
+
+	let Some(task_collection) = implicit_task_collection.get_mut("tasks") else {
+		return Err(eyre!(
+			"invalid task collection: {path}\n'tasks' is required when defining tasks as a mapping"
+		));
+	};
+
+	if task_collection.is_string() || is_task_reference_mapping(task_collection) {
 		*task_collection = Value::Sequence(vec![task_collection.clone()]);
 	}
 
@@ -438,10 +1101,10 @@ pub fn expand_task_collection<'a>(
 			.as_sequence()
 			.unwrap()
 			.iter()
-			.all(|i| i.is_string())
+			.all(|i| i.is_string() || is_task_reference_mapping(i))
 	{
 		return Err(eyre!(
-			"invalid command format in yaml: {task_collection:#?}\ncommand must be a string or array of strings",
+			"invalid command format in yaml: {task_collection:#?}\ncommand must be a string, a '{{ run: <command> }}' reference or an array of them",
 		));
 	}
 
@@ -449,24 +1112,62 @@ pub fn expand_task_collection<'a>(
 	*task_sequence = task_sequence
 		.iter_mut()
 		.flat_map(|i| {
-			i.as_str()
-				.unwrap()
-				.split("&&")
-				.map(|s| Value::String(s.trim().to_string()))
+			// references are kept as '@<command>' from here on
+			let task = match i.get("run") {
+				Some(command) => {
+					let command = command.as_str().unwrap();
+
+					if command.is_empty()
+						|| command.contains(char::is_whitespace)
+						|| template::has_placeholders(command)
+					{
+						return vec![Err(invalid_reference_error(path, command))];
+					}
+
+					format!("{}{command}", constants::TASK_REFERENCE_PREFIX)
+				}
+				None => i.as_str().unwrap().to_string(),
+			};
+
+			task.split("&&")
+				.map(|s| Ok(Value::String(s.trim().to_string())))
+				.collect::<Vec<_>>()
 		})
-		.collect();
+		.collect::<Result<_>>()?;
+
+	// '@' followed by whitespace is a plain shell task, as in '@echo off'
+	for task in task_sequence.iter().map(|task| task.as_str().unwrap()) {
+		if let Some(command) = names::get_task_reference(task)
+			&& template::has_placeholders(command)
+		{
+			return Err(invalid_reference_error(path, command));
+		}
+	}
 
-	println!("{path} - task collection expanded");
+	log_debug!("{path} - task collection expanded");
 
 	Ok(implicit_task_collection)
 }
 
+fn invalid_reference_error(path: &str, command: &str) -> Report {
+	eyre!(
+		"invalid task reference in '{path}': '{}{command}'\na reference must be the name of a command, without spaces or placeholders",
+		constants::TASK_REFERENCE_PREFIX
+	)
+}
+
+fn is_task_reference_mapping(value: &Value) -> bool {
+	value.as_mapping().is_some_and(|mapping| {
+		mapping.len() == 1 && mapping.get("run").is_some_and(Value::is_string)
+	})
+}
+
 pub fn expand_potential_path<'a>(
 	key_path: &str,
 	config_dir: &str,
 	value: &'a mut Value,
 ) -> Result<&'a mut Value> {
-	println!("{key_path} - expanding path/workspace");
+	log_debug!("{key_path} - expanding path/workspace");
 
 	if !value.is_string() {
 		return Err(eyre!(
@@ -480,17 +1181,117 @@ pub fn expand_potential_path<'a>(
 		return Ok(value);
 	}
 
+	// relative paths are anchored to the enclosing scope's directory, which is only known once the
+	// whole config has been desugared (see resolve_relative_dirs)
+	if path_util::is_relative_path(value_str) {
+		log_debug!("{key_path} - path deferred until the enclosing scope is known");
+
+		return Ok(value);
+	}
+
+	// templated values can only be resolved once their variables are known, at run time
+	if template::has_placeholders(value_str) {
+		log_debug!("{key_path} - path/workspace deferred until variables are resolved");
+
+		return Ok(value);
+	}
+
 	if let Some(path) = path_util::try_get_path(value_str, Some(config_dir.to_string()))? {
 		process_path(value, path)?;
 	} else {
 		*value = Value::String(format!("ws:{value_str}"));
 	}
 
-	println!("{key_path} - path/workspace expanded");
+	log_debug!("{key_path} - path/workspace expanded");
 
 	Ok(value)
 }
 
+// anchors every relative 'in' to the directory of its enclosing scope, falling back to the config
+// directory for the root scope. runs after desugaring, so 'in' set through shorthands is included
+pub fn resolve_relative_dirs(path: &str, base_dir: &str, scope: &mut Mapping) -> Result<()> {
+	resolve_scope_dirs(path, &Value::String(base_dir.to_string()), scope)
+}
+
+fn resolve_scope_dirs(path: &str, base_dir: &Value, scope: &mut Mapping) -> Result<()> {
+	let scope_dir = if let Some(value) = scope.get_mut("in") {
+		resolve_relative_dir(format!("{path}.in").as_str(), base_dir, value)?;
+
+		value.clone()
+	} else {
+		base_dir.clone()
+	};
+
+	for (key, value) in scope.iter_mut() {
+		let key = key.as_str().unwrap();
+
+		match key {
+			"pre" | "post" => {
+				if let Some(value) = value.get_mut("in") {
+					resolve_relative_dir(format!("{path}.{key}.in").as_str(), &scope_dir, value)?;
+				}
+			}
+			"commands" => {
+				for (command_key, command) in value.as_mapping_mut().unwrap().iter_mut() {
+					if let Some(value) = command.get_mut("in") {
+						resolve_relative_dir(
+							format!("{path}.commands.{}.in", command_key.as_str().unwrap())
+								.as_str(),
+							&scope_dir,
+							value,
+						)?;
+					}
+				}
+			}
+			_ if constants::SCOPE_RESERVED_KEYS.contains(&key) => {}
+			_ => {
+				if let Some(sub_scope) = value.as_mapping_mut() {
+					resolve_scope_dirs(format!("{path}.{key}").as_str(), &scope_dir, sub_scope)?;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn resolve_relative_dir(key_path: &str, base_dir: &Value, value: &mut Value) -> Result<()> {
+	let Some(value_str) = value.as_str() else {
+		return Err(eyre!(
+			"key value is invalid type in mapping: {key_path:#?}\nkey value must be a string"
+		));
+	};
+
+	// a templated value, or a relative one in a templated scope, is only known once it's rendered.
+	// it's kept as a '{ base, template }' mapping along with the directory it resolves against
+	if template::has_placeholders(value_str)
+		|| (path_util::is_relative_path(value_str) && base_dir.is_mapping())
+	{
+		let mut templated_dir = Mapping::new();
+		templated_dir.insert("base".into(), base_dir.clone());
+		templated_dir.insert("template".into(), value_str.into());
+
+		*value = Value::Mapping(templated_dir);
+
+		return Ok(());
+	}
+
+	let base_dir = base_dir.as_str().unwrap();
+
+	if path_util::is_relative_path(value_str) {
+		// a workspace reference can't be resolved yet, so the rest of the path is carried along with it
+		*value = Value::String(if base_dir.starts_with("ws:") {
+			format!("{base_dir}/{value_str}")
+		} else {
+			Utf8Path::new(base_dir).join(value_str).into_string()
+		});
+	}
+
+	expand_potential_path(key_path, base_dir, value)?;
+
+	Ok(())
+}
+
 pub fn process_path(value: &mut Value, path: Utf8PathBuf) -> Result<&mut Value> {
 	let path_str = path.clone().into_string();
 
@@ -504,3 +1305,46 @@ pub fn process_path(value: &mut Value, path: Utf8PathBuf) -> Result<&mut Value>
 
 	Ok(value)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expand_command(yaml: &str) -> Result<Value> {
+		let mut command = serde_yaml::from_str(yaml).unwrap();
+		expand_task_collection(
+			"commands.gen",
+			&mut command,
+			&constants::COMMAND_ENTRY_SCOPE_KEYS,
+		)?;
+
+		Ok(command)
+	}
+
+	#[test]
+	fn keeps_sources_and_outputs_of_command_entries() {
+		let command =
+			expand_command("{ sources: src/*.txt, outputs: [gen/out.txt], tasks: cat }").unwrap();
+
+		assert_eq!(
+			command,
+			serde_yaml::from_str::<Value>(
+				"{ sources: [src/*.txt], outputs: [gen/out.txt], tasks: [cat] }"
+			)
+			.unwrap()
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_task_collection_keys() {
+		let err = expand_command("{ source: [src], tasks: [cat] }").unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"unable to process unknown key: commands.gen.source\nexpected one of: in, tasks, background, parallel, options, foreach, needs, sources, outputs"
+		);
+
+		let mut pre = serde_yaml::from_str("{ sources: [src], tasks: [cat] }").unwrap();
+		assert!(expand_task_collection("commands.pre", &mut pre, &[]).is_err());
+	}
+}
@@ -0,0 +1,70 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::{log_debug, util::path as path_util};
+
+// removes the 'include' key from a config and resolves it to the files it refers to, in the
+// order they were listed. globs are expanded in sorted order, so the result is stable
+pub fn take_includes(path: &str, config_dir: &str, config: &mut Mapping) -> Result<Vec<String>> {
+	let patterns = match config.remove("include") {
+		None => return Ok(Vec::new()),
+		Some(Value::String(pattern)) => vec![pattern],
+		Some(Value::Sequence(sequence)) => sequence
+			.into_iter()
+			.map(|item| match item {
+				Value::String(pattern) => Ok(pattern),
+				_ => Err(eyre!(
+					"{path} - invalid 'include' item: {item:?}\nitems must be paths or globs"
+				)),
+			})
+			.collect::<Result<Vec<_>>>()?,
+		Some(value) => {
+			return Err(eyre!(
+				"{path} - invalid 'include': {value:?}\n'include' must be a path, a glob or a list of them"
+			));
+		}
+	};
+
+	let mut include_paths = Vec::new();
+
+	for pattern in patterns {
+		log_debug!("{path} - resolving include '{pattern}'");
+
+		let full_pattern = Utf8Path::new(config_dir).join(&pattern);
+
+		// plain paths must exist, while a glob is allowed to match nothing
+		if !path_util::is_glob(&pattern) {
+			let include_path = dunce::canonicalize(&full_pattern)
+				.map_err(|err| eyre!("{path} - unable to include '{pattern}'\n{err}"))?;
+
+			push_include(&mut include_paths, include_path)?;
+			continue;
+		}
+
+		let mut matches = glob::glob(full_pattern.as_str())
+			.map_err(|err| eyre!("{path} - invalid include glob '{pattern}'\n{err}"))?
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|err| eyre!("{path} - unable to include '{pattern}'\n{err}"))?;
+
+		matches.sort();
+
+		for include_path in matches.into_iter().filter(|path| path.is_file()) {
+			push_include(&mut include_paths, dunce::canonicalize(include_path)?)?;
+		}
+	}
+
+	Ok(include_paths)
+}
+
+fn push_include(include_paths: &mut Vec<String>, include_path: std::path::PathBuf) -> Result<()> {
+	let include_path = Utf8PathBuf::from_path_buf(include_path)
+		.map_err(|path| eyre!("unable to process non UTF-8 path: {path:?}"))?
+		.into_string();
+
+	if !include_paths.contains(&include_path) {
+		include_paths.push(include_path);
+	}
+
+	Ok(())
+}
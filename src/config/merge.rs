@@ -0,0 +1,249 @@
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::config::{constants, parse::ConfigLayer};
+
+// merges a project config with the files it includes, where two files defining the same thing
+// differently is an error rather than one silently winning
+pub fn merge_included_layers(layers: &[ConfigLayer]) -> Result<Mapping> {
+	let mut config = Mapping::new();
+
+	for (i, layer) in layers.iter().enumerate() {
+		if let Err(key_path) = merge_exclusive(&mut config, &layer.config, &mut Vec::new()) {
+			let previous_path = layers[..i]
+				.iter()
+				.find(|previous_layer| get_key_path(&previous_layer.config, &key_path).is_some())
+				.map(|previous_layer| previous_layer.path.as_str())
+				.unwrap_or_default();
+
+			return Err(eyre!(
+				"conflicting definitions of '{}'\n- defined in '{previous_path}'\n- defined again in '{}'",
+				key_path.join("."),
+				layer.path
+			));
+		}
+	}
+
+	Ok(config)
+}
+
+// returns the path of the first key both mappings define differently
+fn merge_exclusive(
+	base: &mut Mapping,
+	merger: &Mapping,
+	key_path: &mut Vec<String>,
+) -> Result<(), Vec<String>> {
+	// entries of these are whole definitions, so they are never combined with one another. the
+	// root 'commands' key is the root scope rather than a commands mapping
+	let is_atomic = key_path.len() > 1
+		&& key_path
+			.last()
+			.is_some_and(|key| matches!(key.as_str(), "commands" | "variables" | "options"));
+
+	for (key, value) in merger {
+		let Some(base_value) = base.get_mut(key) else {
+			base.insert(key.clone(), value.clone());
+			continue;
+		};
+
+		key_path.push(key.as_str().unwrap().to_string());
+
+		match (base_value, value) {
+			(Value::Mapping(base_mapping), Value::Mapping(merger_mapping)) if !is_atomic => {
+				merge_exclusive(base_mapping, merger_mapping, key_path)?;
+			}
+			(base_value, value) if *base_value != *value => return Err(key_path.clone()),
+			_ => {}
+		}
+
+		key_path.pop();
+	}
+
+	Ok(())
+}
+
+fn get_key_path<'a>(mapping: &'a Mapping, key_path: &[String]) -> Option<&'a Value> {
+	let (first, rest) = key_path.split_first()?;
+
+	rest.iter()
+		.try_fold(mapping.get(first.as_str())?, |value, key| {
+			value.as_mapping()?.get(key.as_str())
+		})
+}
+
+// merges a lower precedence config into `base`, anything `base` already defines is kept
+pub fn merge_config(base: &mut Mapping, merger: &Mapping) {
+	let Some(merger_commands) = merger.get("commands").and_then(|value| value.as_mapping()) else {
+		return;
+	};
+
+	if let Some(base_commands) = base
+		.get_mut("commands")
+		.and_then(|value| value.as_mapping_mut())
+	{
+		merge_scope(base_commands, merger_commands);
+	} else {
+		base.insert("commands".into(), merger_commands.clone().into());
+	}
+}
+
+pub fn merge_scope(base: &mut Mapping, merger: &Mapping) {
+	let merger = &without_shadowed_commands(base, merger);
+
+	for (key, value) in merger {
+		let Some(base_value) = base.get_mut(key) else {
+			base.insert(key.clone(), value.clone());
+			continue;
+		};
+
+		// keys are all strings after expanding
+		match key.as_str().unwrap() {
+			// commands and variables are merged per entry, but an entry is never combined with another
+			"commands" | "variables" => {
+				if let (Some(base_entries), Some(merger_entries)) =
+					(base_value.as_mapping_mut(), value.as_mapping())
+				{
+					for (entry_key, entry_value) in merger_entries {
+						if !base_entries.contains_key(entry_key) {
+							base_entries.insert(entry_key.clone(), entry_value.clone());
+						}
+					}
+				}
+			}
+			key if constants::SCOPE_RESERVED_KEYS.contains(&key) => {}
+			_ => {
+				if let (Some(base_scope), Some(merger_scope)) =
+					(base_value.as_mapping_mut(), value.as_mapping())
+				{
+					merge_scope(base_scope, merger_scope);
+				}
+			}
+		}
+	}
+}
+
+// a command can be an entry of a scope's 'commands' or the '.' command of a scope named after it,
+// so a command `base` defines in one form hides the merger's command of the same name in the other
+fn without_shadowed_commands(base: &Mapping, merger: &Mapping) -> Mapping {
+	let mut merger = merger.clone();
+
+	if let Some(Value::Mapping(merger_commands)) = merger.get_mut("commands") {
+		merger_commands.retain(|name, _| !has_scope_command(base, name.as_str().unwrap()));
+	}
+
+	let shadowed_scopes = merger
+		.keys()
+		.map(|key| key.as_str().unwrap().to_string())
+		.filter(|key| {
+			!constants::SCOPE_RESERVED_KEYS.contains(&key.as_str())
+				&& has_command_entry(base, key)
+				&& has_scope_command(&merger, key)
+		})
+		.collect::<Vec<_>>();
+
+	for key in shadowed_scopes {
+		let scope = merger
+			.get_mut(key.as_str())
+			.unwrap()
+			.as_mapping_mut()
+			.unwrap();
+		let commands = scope.get_mut("commands").unwrap().as_mapping_mut().unwrap();
+		commands.shift_remove(".");
+
+		if commands.is_empty() {
+			scope.shift_remove("commands");
+		}
+	}
+
+	merger
+}
+
+fn has_command_entry(scope: &Mapping, name: &str) -> bool {
+	scope
+		.get("commands")
+		.and_then(Value::as_mapping)
+		.is_some_and(|commands| commands.contains_key(name))
+}
+
+fn has_scope_command(scope: &Mapping, name: &str) -> bool {
+	!constants::SCOPE_RESERVED_KEYS.contains(&name)
+		&& scope
+			.get(name)
+			.and_then(Value::as_mapping)
+			.is_some_and(|sub_scope| has_command_entry(sub_scope, "."))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn layer(path: &str, yaml: &str) -> ConfigLayer {
+		ConfigLayer {
+			path: path.to_string(),
+			config: serde_yaml::from_str(yaml).unwrap(),
+		}
+	}
+
+	fn merge(layers: &[ConfigLayer]) -> Result<String> {
+		Ok(serde_yaml::to_string(&merge_included_layers(layers)?)?)
+	}
+
+	#[test]
+	fn combines_layers_defining_different_things() {
+		let merged = merge(&[
+			layer(
+				"project.yaml",
+				"commands: { api: { commands: { build: a } } }",
+			),
+			layer("web.yaml", "commands: { web: { commands: { build: b } } }"),
+		])
+		.unwrap();
+
+		assert_eq!(
+			merged,
+			"commands:\n  api:\n    commands:\n      build: a\n  web:\n    commands:\n      build: b\n"
+		);
+	}
+
+	#[test]
+	fn allows_identical_definitions() {
+		let yaml = "commands: { variables: { port: { value: 80 } } }";
+
+		merge(&[layer("a.yaml", yaml), layer("b.yaml", yaml)]).unwrap();
+	}
+
+	#[test]
+	fn reports_conflicting_commands_with_both_files() {
+		let err = merge(&[
+			layer(
+				"project.yaml",
+				"commands: { api: { commands: { build: a } } }",
+			),
+			layer("other.yaml", "commands: { web: { commands: { test: c } } }"),
+			layer("api.yaml", "commands: { api: { commands: { build: b } } }"),
+		])
+		.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"conflicting definitions of 'commands.api.commands.build'\n- defined in 'project.yaml'\n- defined again in 'api.yaml'"
+		);
+	}
+
+	#[test]
+	fn never_combines_entries_of_atomic_keys() {
+		let err = merge(&[
+			layer("a.yaml", "commands: { variables: { port: { value: 80 } } }"),
+			layer(
+				"b.yaml",
+				"commands: { variables: { port: { value: 80, description: x } } }",
+			),
+		])
+		.unwrap_err();
+
+		assert!(
+			err.to_string()
+				.starts_with("conflicting definitions of 'commands.variables.port'")
+		);
+	}
+}
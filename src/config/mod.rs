@@ -1,5 +1,12 @@
 pub mod autocapture;
 pub mod constants;
 pub mod desugar;
+pub mod discover;
 pub mod expand;
+pub mod include;
+pub mod merge;
+pub mod names;
+pub mod nested;
 pub mod parse;
+pub mod references;
+pub mod settings;
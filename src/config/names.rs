@@ -0,0 +1,60 @@
+use serde_yaml::{Mapping, Value};
+
+use crate::config::{constants, settings};
+
+// the full name of a command is the path of its scope joined by the scope separator, followed by its
+// key. a scope's own command is keyed '.' and is named after the scope
+pub fn get_command_name(scope_path: &[String], key: &str) -> String {
+	let scope_separator = settings::get().scope_separator.as_str();
+
+	if scope_path.is_empty() {
+		key.to_string()
+	} else if key == "." {
+		scope_path.join(scope_separator)
+	} else {
+		format!("{}{scope_separator}{key}", scope_path.join(scope_separator))
+	}
+}
+
+// the full name of every command the config defines, in the order they are defined
+pub fn get_command_names(config: &Mapping) -> Vec<String> {
+	let mut command_names = Vec::new();
+
+	if let Some(Value::Mapping(root_scope)) = config.get("commands") {
+		collect_command_names(&[], root_scope, &mut command_names);
+	}
+
+	command_names
+}
+
+fn collect_command_names(scope_path: &[String], scope: &Mapping, command_names: &mut Vec<String>) {
+	for (key, value) in scope {
+		let key = key.as_str().unwrap();
+
+		if key == "commands"
+			&& let Value::Mapping(commands) = value
+		{
+			for command_key in commands.keys() {
+				command_names.push(get_command_name(scope_path, command_key.as_str().unwrap()));
+			}
+		}
+
+		if constants::SCOPE_RESERVED_KEYS.contains(&key) {
+			continue;
+		}
+
+		if let Value::Mapping(sub_scope) = value {
+			let mut sub_scope_path = scope_path.to_vec();
+			sub_scope_path.push(key.to_string());
+
+			collect_command_names(&sub_scope_path, sub_scope, command_names);
+		}
+	}
+}
+
+// the command a task refers to when it's written as '@<command>'. a task with whitespace after the
+// '@' is an ordinary shell command, as in '@echo off'
+pub fn get_task_reference(task: &str) -> Option<&str> {
+	task.strip_prefix(constants::TASK_REFERENCE_PREFIX)
+		.filter(|command| !command.is_empty() && !command.contains(char::is_whitespace))
+}
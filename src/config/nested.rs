@@ -0,0 +1,96 @@
+use camino::Utf8Path;
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+	config::{
+		constants, merge,
+		parse::{self, ConfigLayer},
+		settings,
+	},
+	log_debug,
+	models::workspace::{self, Workspace},
+	util::yaml,
+};
+
+// mounts the commands of any project config found in a workspace directory as a scope named after
+// the workspace, running in the workspace directory unless it says otherwise. anything the root
+// config defines for that scope wins
+pub fn mount_workspace_configs(config: &mut Mapping, layers: &mut Vec<ConfigLayer>) -> Result<()> {
+	let mut workspace_configs = Vec::new();
+	for Workspace { name, path, .. } in workspace::get_workspaces(config) {
+		let Some(config_path) = constants::CONFIG_FILE_NAMES
+			.iter()
+			.map(|file_name| Utf8Path::new(&path).join(file_name))
+			.find(|config_path| config_path.is_file())
+		else {
+			continue;
+		};
+
+		// the project config itself, or a file it already includes
+		if layers.iter().any(|layer| layer.path == config_path) {
+			continue;
+		}
+
+		workspace_configs.push((name, path, config_path.into_string()));
+	}
+
+	workspace_configs.sort();
+
+	for (name, path, config_path) in workspace_configs {
+		log_debug!("workspaces - mounting '{config_path}' as '{name}'");
+
+		let workspace_config: Mapping = yaml::load_yaml(&config_path)?;
+
+		if workspace_config.contains_key("settings") {
+			return Err(eyre!(
+				"{config_path} - 'settings' can only be defined in the project config or the global config, not in a workspace config"
+			));
+		}
+
+		let scope_separator = settings::get().scope_separator.as_str();
+		if name.contains(scope_separator) {
+			return Err(eyre!(
+				"unable to mount '{config_path}' as '{name}'\nworkspace name contains the scope separator '{scope_separator}'"
+			));
+		}
+
+		let workspace_layers = parse::parse_project_config(&config_path, workspace_config)?;
+
+		merge::merge_config(
+			config,
+			&mount_scope(
+				&name,
+				&path,
+				&merge::merge_included_layers(&workspace_layers)?,
+			),
+		);
+
+		layers.extend(workspace_layers.into_iter().map(|layer| ConfigLayer {
+			config: mount_scope(&name, &path, &layer.config),
+			path: layer.path,
+		}));
+	}
+
+	Ok(())
+}
+
+// wraps the root scope of a workspace config in a scope for the workspace
+fn mount_scope(name: &str, path: &str, workspace_config: &Mapping) -> Mapping {
+	let mut scope = match workspace_config.get("commands") {
+		Some(Value::Mapping(commands)) => commands.clone(),
+		_ => Mapping::new(),
+	};
+
+	if !scope.contains_key("in") {
+		scope.insert("in".into(), path.into());
+	}
+
+	let mut root_scope = Mapping::new();
+	root_scope.insert(name.into(), scope.into());
+
+	let mut mounted_config = Mapping::new();
+	mounted_config.insert("commands".into(), root_scope.into());
+
+	mounted_config
+}
@@ -1,12 +1,147 @@
+use camino::Utf8Path;
 use color_eyre::{Report, Result, eyre::eyre};
-use serde_yaml::Mapping;
+use serde_yaml::{Mapping, Value};
 
 use crate::{
-	config::{autocapture, desugar, expand},
-	util::{path as path_util, yaml},
+	config::{
+		autocapture, constants, desugar, discover, expand, include, merge, nested, references,
+		settings::{self, Settings},
+	},
+	log_debug,
+	models::workspace,
+	util::{log, path as path_util, yaml},
 };
 
-pub fn parse_project_config(path: &str) -> Result<Mapping> {
+pub struct ConfigLayer {
+	pub path: String,
+	pub config: Mapping,
+}
+
+pub struct LoadedConfig {
+	// every layer merged together, which is what commands are run from
+	pub config: Mapping,
+	// in order of precedence, so the project config comes first
+	pub layers: Vec<ConfigLayer>,
+}
+
+pub fn load_config(project_config_path: &str) -> Result<LoadedConfig> {
+	let global_config_path = discover::find_global_config()?;
+
+	let global_config: Option<Mapping> = global_config_path
+		.as_deref()
+		.map(yaml::load_yaml)
+		.transpose()?;
+	let project_config: Mapping = yaml::load_yaml(project_config_path)?;
+
+	// settings apply to processing both config files, so they are read before either is expanded
+	let mut settings = Settings::default();
+	if let (Some(path), Some(config)) = (&global_config_path, &global_config) {
+		settings.apply_config(path, config)?;
+	}
+	settings.apply_config(project_config_path, &project_config)?;
+
+	log::set_level(settings.log_level);
+	settings::init(settings)?;
+
+	let global_layer = if let (Some(path), Some(config)) = (global_config_path, global_config) {
+		Some(ConfigLayer {
+			config: parse_global_config(&path, config)?,
+			path,
+		})
+	} else {
+		None
+	};
+
+	let mut layers = parse_project_config(project_config_path, project_config)?;
+
+	let mut config = merge::merge_included_layers(&layers)?;
+
+	let project_config_dir = Utf8Path::new(&layers[0].path)
+		.parent()
+		.ok_or_else(|| {
+			eyre!(
+				"unable to get directory for config file path: '{}'",
+				layers[0].path
+			)
+		})?
+		.to_path_buf();
+
+	// workspaces are only auto captured from the project root, once every included file has defined its own
+	if let Some(workspaces) = config
+		.get_mut("workspaces")
+		.and_then(|value| value.as_mapping_mut())
+	{
+		autocapture::auto_capture_workspaces(
+			"workspaces",
+			project_config_dir.as_str(),
+			workspaces,
+		)?;
+	}
+
+	nested::mount_workspace_configs(&mut config, &mut layers)?;
+
+	// a broken dependency graph is reported straight away, rather than once a command fans out
+	workspace::get_dependency_order(&workspace::get_workspaces(&config))?;
+
+	if let Some(global_layer) = global_layer {
+		merge::merge_config(&mut config, &global_layer.config);
+		layers.push(global_layer);
+	}
+
+	// commands run from the project root unless they say otherwise, wherever the config was found from
+	if let Some(commands) = config
+		.get_mut("commands")
+		.and_then(|value| value.as_mapping_mut())
+		&& !commands.contains_key("in")
+	{
+		commands.insert("in".into(), project_config_dir.as_str().into());
+	}
+
+	// references can point to commands from any layer, so they're only resolved once all are merged
+	references::resolve_command_references(&mut config)?;
+
+	Ok(LoadedConfig { config, layers })
+}
+
+pub fn parse_global_config(path: &str, mut global_config: Mapping) -> Result<Mapping> {
+	let config_dir = Utf8Path::new(path)
+		.parent()
+		.ok_or_else(|| eyre!("unable to get directory for config file path: '{path}'"))?
+		.to_string();
+
+	log_debug!("processing global config file: '{path}'");
+
+	expand::expand_internal_config(&config_dir, &mut global_config)?;
+
+	if let Some(commands) = global_config
+		.get_mut("commands")
+		.and_then(|value| value.as_mapping_mut())
+	{
+		desugar::desugar_mapping("commands", &config_dir, commands)?;
+		expand::resolve_relative_dirs("commands", &config_dir, commands)?;
+
+		check_scope_keys(path, "commands", commands)?;
+	}
+
+	Ok(global_config)
+}
+
+// parses the project config and every file it includes into their own layers, the project config
+// comes first and is followed by its includes, depth first
+pub fn parse_project_config(path: &str, project_config: Mapping) -> Result<Vec<ConfigLayer>> {
+	let mut layers = Vec::new();
+
+	parse_config_file(path, project_config, &mut Vec::new(), &mut layers)?;
+
+	Ok(layers)
+}
+
+fn parse_config_file(
+	path: &str,
+	mut project_config: Mapping,
+	include_stack: &mut Vec<String>,
+	layers: &mut Vec<ConfigLayer>,
+) -> Result<()> {
 	let project_config_path =
 		path_util::try_get_path(path, None)?.ok_or_else(|| eyre!("unable to find '{path}'"))?;
 	let project_config_path_str = project_config_path.clone().into_string();
@@ -18,40 +153,99 @@ pub fn parse_project_config(path: &str) -> Result<Mapping> {
 		.to_path_buf()
 		.into_string();
 
-	println!("processing config file: '{project_config_path_str}'");
+	log_debug!("processing config file: '{project_config_path_str}'");
 
-	let mut project_config: Mapping = yaml::load_yaml(&project_config_path_str)?;
+	let includes = include::take_includes(
+		&project_config_path_str,
+		&project_config_path_dir_str,
+		&mut project_config,
+	)?;
 
 	expand::expand_project_config(&project_config_path_dir_str, &mut project_config)?;
 
-	// can use unwrap for expecting key and value types after expanding, as any invalid key or value types would have thrown an error in the expansion step
-	for (key, value) in project_config.iter_mut() {
-		match key.as_str().unwrap() {
-			"commands" => {
-				let commands = value.as_mapping_mut().unwrap();
+	if let Some(commands) = project_config
+		.get_mut("commands")
+		.and_then(|value| value.as_mapping_mut())
+	{
+		desugar::desugar_mapping("commands", &project_config_path_dir_str, commands)?;
+		expand::resolve_relative_dirs("commands", &project_config_path_dir_str, commands)?;
 
-				desugar::desugar_mapping("commands", &project_config_path_dir_str, commands)?;
+		check_scope_keys(&project_config_path_str, "commands", commands)?;
+	}
 
-				Ok::<_, Report>(())
-			}
-			"workspaces" => {
-				let workspaces = value.as_mapping_mut().unwrap();
+	layers.push(ConfigLayer {
+		path: project_config_path_str.clone(),
+		config: project_config,
+	});
+
+	include_stack.push(project_config_path_str);
+
+	for include_path in includes {
+		if let Some(cycle_start) = include_stack.iter().position(|path| *path == include_path) {
+			return Err(eyre!(
+				"include cycle detected: {} -> {include_path}",
+				include_stack[cycle_start..].join(" -> ")
+			));
+		}
 
-				autocapture::auto_capture_workspaces(
-					"workspaces",
-					&project_config_path_dir_str,
-					workspaces,
-				)?;
+		// a file included through several others is only loaded once
+		if layers.iter().any(|layer| layer.path == include_path) {
+			continue;
+		}
 
-				Ok(())
+		let included_config: Mapping = yaml::load_yaml(&include_path)?;
+
+		if included_config.contains_key("settings") {
+			return Err(eyre!(
+				"{include_path} - 'settings' can only be defined in the project config or the global config, not in an included file"
+			));
+		}
+
+		parse_config_file(&include_path, included_config, include_stack, layers)?;
+	}
+
+	include_stack.pop();
+
+	Ok(())
+}
+
+// joined command names are split on the scope separator again, so no key can contain it
+fn check_scope_keys(config_path: &str, path: &str, scope: &Mapping) -> Result<()> {
+	let scope_separator = settings::get().scope_separator.as_str();
+
+	if let Some(Value::Mapping(commands)) = scope.get("commands") {
+		for key in commands.keys() {
+			let key = key.as_str().unwrap();
+
+			// the default command key is never part of a joined name
+			if key != "." && key.contains(scope_separator) {
+				return Err(scope_separator_error(config_path, path, key));
 			}
-			_ => Ok(()),
-		}?
+		}
 	}
 
-	if let Some(commands) = project_config["commands"].as_mapping_mut() {
-		desugar::desugar_mapping("commands", &project_config_path_dir_str, commands)?;
+	for (key, value) in scope {
+		let key = key.as_str().unwrap();
+
+		if constants::SCOPE_RESERVED_KEYS.contains(&key) {
+			continue;
+		}
+
+		if key.contains(scope_separator) {
+			return Err(scope_separator_error(config_path, path, key));
+		}
+
+		if let Value::Mapping(sub_scope) = value {
+			check_scope_keys(config_path, format!("{path}.{key}").as_str(), sub_scope)?;
+		}
 	}
 
-	Ok(project_config)
+	Ok(())
+}
+
+fn scope_separator_error(config_path: &str, path: &str, key: &str) -> Report {
+	eyre!(
+		"invalid key '{key}' in '{path}' ({config_path})\nkeys can't contain the scope separator '{}', rename the key or set a different 'scope_separator'",
+		settings::get().scope_separator
+	)
 }
@@ -0,0 +1,484 @@
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+	config::{constants, names, settings},
+	log_debug,
+};
+
+// the commands a command needs and refers to in its tasks, including the ones its enclosing scopes
+// add through 'needs', 'pre' and 'post'
+struct CommandReferences {
+	command: String,
+	references: Vec<String>,
+}
+
+#[derive(Default)]
+struct ScopeReferences {
+	pre: Vec<String>,
+	post: Vec<String>,
+	needs: Vec<String>,
+}
+
+// rewrites every '@<command>' task and 'needs' entry to the full name of the command it refers to,
+// looking in the scope it's used in first and then in each enclosing scope. unknown commands and
+// reference cycles are errors
+pub fn resolve_command_references(config: &mut Mapping) -> Result<()> {
+	let command_names = names::get_command_names(config);
+
+	let Some(Value::Mapping(root_scope)) = config.get_mut("commands") else {
+		return Ok(());
+	};
+
+	let mut command_references = Vec::new();
+	resolve_scope_references(
+		&[],
+		root_scope,
+		&command_names,
+		&ScopeReferences::default(),
+		&mut command_references,
+	)?;
+
+	let mut checked_commands = Vec::new();
+	for references in &command_references {
+		check_reference_cycles(
+			&references.command,
+			&command_references,
+			&mut Vec::new(),
+			&mut checked_commands,
+		)?;
+	}
+
+	Ok(())
+}
+
+fn check_reference_cycles<'a>(
+	command: &'a str,
+	command_references: &'a [CommandReferences],
+	reference_path: &mut Vec<&'a str>,
+	checked_commands: &mut Vec<&'a str>,
+) -> Result<()> {
+	if let Some(cycle_start) = reference_path.iter().position(|path| *path == command) {
+		let mut cycle = reference_path[cycle_start..].to_vec();
+		cycle.push(command);
+
+		return Err(eyre!(
+			"command reference cycle detected: {}",
+			cycle.join(" -> ")
+		));
+	}
+
+	if checked_commands.contains(&command) {
+		return Ok(());
+	}
+
+	let Some(references) = command_references
+		.iter()
+		.find(|references| references.command == command)
+	else {
+		return Ok(());
+	};
+
+	reference_path.push(command);
+
+	for reference in &references.references {
+		check_reference_cycles(
+			reference,
+			command_references,
+			reference_path,
+			checked_commands,
+		)?;
+	}
+
+	reference_path.pop();
+	checked_commands.push(command);
+
+	Ok(())
+}
+
+fn resolve_scope_references(
+	scope_path: &[String],
+	scope: &mut Mapping,
+	command_names: &[String],
+	inherited: &ScopeReferences,
+	command_references: &mut Vec<CommandReferences>,
+) -> Result<()> {
+	let location = if scope_path.is_empty() {
+		"root".to_string()
+	} else {
+		scope_path.join(settings::get().scope_separator.as_str())
+	};
+
+	// pre and post tasks and needed commands accumulate down the scopes, unless a scope resets them
+	let mut scope_references = ScopeReferences::default();
+	for (key, references, inherited_references) in [
+		("pre", &mut scope_references.pre, &inherited.pre),
+		("post", &mut scope_references.post, &inherited.post),
+		("needs", &mut scope_references.needs, &inherited.needs),
+	] {
+		if !scope.contains_key(format!("-{key}")) {
+			references.extend(inherited_references.iter().cloned());
+		}
+	}
+
+	if let Some(pre) = scope.get_mut("pre") {
+		scope_references.pre.extend(resolve_collection_references(
+			format!("{location} (pre)").as_str(),
+			scope_path,
+			pre,
+			command_names,
+		)?);
+	}
+
+	if let Some(post) = scope.get_mut("post") {
+		scope_references.post.extend(resolve_collection_references(
+			format!("{location} (post)").as_str(),
+			scope_path,
+			post,
+			command_names,
+		)?);
+	}
+
+	if let Some(needs) = scope.get_mut("needs") {
+		scope_references.needs.extend(resolve_needs_references(
+			format!("{location} (needs)").as_str(),
+			scope_path,
+			needs,
+			command_names,
+		)?);
+	}
+
+	if let Some(Value::Mapping(commands)) = scope.get_mut("commands") {
+		for (key, command) in commands.iter_mut() {
+			let command_name = names::get_command_name(scope_path, key.as_str().unwrap());
+
+			// a command entry can add needs of its own, or reset the ones of its scope
+			let mut references = if command.get("-needs").is_some() {
+				Vec::new()
+			} else {
+				scope_references.needs.clone()
+			};
+
+			if let Some(needs) = command.get_mut("needs") {
+				references.extend(resolve_needs_references(
+					format!("{command_name} (needs)").as_str(),
+					scope_path,
+					needs,
+					command_names,
+				)?);
+			}
+
+			references.extend(scope_references.pre.iter().cloned());
+			references.extend(resolve_collection_references(
+				&command_name,
+				scope_path,
+				command,
+				command_names,
+			)?);
+			references.extend(scope_references.post.iter().cloned());
+
+			command_references.push(CommandReferences {
+				command: command_name,
+				references,
+			});
+		}
+	}
+
+	for (key, value) in scope.iter_mut() {
+		let key = key.as_str().unwrap();
+
+		if constants::SCOPE_RESERVED_KEYS.contains(&key) {
+			continue;
+		}
+
+		if let Value::Mapping(sub_scope) = value {
+			let mut sub_scope_path = scope_path.to_vec();
+			sub_scope_path.push(key.to_string());
+
+			resolve_scope_references(
+				&sub_scope_path,
+				sub_scope,
+				command_names,
+				&scope_references,
+				command_references,
+			)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn resolve_collection_references(
+	location: &str,
+	scope_path: &[String],
+	task_collection: &mut Value,
+	command_names: &[String],
+) -> Result<Vec<String>> {
+	// referenced commands run in the foreground, one after another like any other task
+	let runs_concurrently = ["background", "parallel"]
+		.iter()
+		.any(|key| task_collection.get(key).and_then(Value::as_bool) == Some(true));
+
+	let Some(Value::Sequence(tasks)) = task_collection.get_mut("tasks") else {
+		return Ok(Vec::new());
+	};
+
+	let mut references = Vec::new();
+
+	for task in tasks.iter_mut() {
+		let Some(reference) = task.as_str().and_then(names::get_task_reference) else {
+			continue;
+		};
+
+		if runs_concurrently {
+			return Err(eyre!(
+				"invalid task reference in '{location}': '{}{reference}'\nreferences can't be used in background or parallel tasks",
+				constants::TASK_REFERENCE_PREFIX
+			));
+		}
+
+		let command = resolve_reference(location, scope_path, reference, command_names)?;
+
+		*task = Value::String(format!("{}{command}", constants::TASK_REFERENCE_PREFIX));
+		references.push(command);
+	}
+
+	Ok(references)
+}
+
+fn resolve_needs_references(
+	location: &str,
+	scope_path: &[String],
+	needs: &mut Value,
+	command_names: &[String],
+) -> Result<Vec<String>> {
+	let Value::Sequence(needs) = needs else {
+		return Ok(Vec::new());
+	};
+
+	let mut references = Vec::new();
+
+	for command in needs.iter_mut() {
+		let needed_command = resolve_reference(
+			location,
+			scope_path,
+			command.as_str().unwrap(),
+			command_names,
+		)?;
+
+		*command = Value::String(needed_command.clone());
+		references.push(needed_command);
+	}
+
+	Ok(references)
+}
+
+// the command a name refers to, looking in the scope it's used in first and then in each
+// enclosing scope
+fn resolve_reference(
+	location: &str,
+	scope_path: &[String],
+	reference: &str,
+	command_names: &[String],
+) -> Result<String> {
+	let Some(command) = (0..=scope_path.len())
+		.rev()
+		.map(|depth| names::get_command_name(&scope_path[..depth], reference))
+		.find(|command| command_names.contains(command))
+	else {
+		return Err(eyre!(
+			"unknown command '{reference}' referenced in '{location}'"
+		));
+	};
+
+	log_debug!("{location} - resolved '{reference}' to '{command}'");
+
+	Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn resolve(yaml: &str) -> Result<Mapping> {
+		let mut config = serde_yaml::from_str(yaml).unwrap();
+		resolve_command_references(&mut config)?;
+
+		Ok(config)
+	}
+
+	fn get_tasks(config: &Mapping, path: &[&str]) -> Vec<String> {
+		let task_collection = path
+			.iter()
+			.fold(&config["commands"], |value, key| &value[*key]);
+
+		task_collection["tasks"]
+			.as_sequence()
+			.unwrap()
+			.iter()
+			.map(|task| task.as_str().unwrap().to_string())
+			.collect()
+	}
+
+	#[test]
+	fn resolves_references_to_the_innermost_command() {
+		let config = resolve(
+			"
+commands:
+  commands:
+    build: { tasks: [echo root] }
+  api:
+    commands:
+      build: { tasks: [echo api] }
+      test: { tasks: ['@build', '@lint'] }
+  lint:
+    commands:
+      .: { tasks: [echo lint] }
+",
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_tasks(&config, &["api", "commands", "test"]),
+			["@api:build", "@lint"]
+		);
+	}
+
+	#[test]
+	fn resolves_needs_to_full_names() {
+		let config = resolve(
+			"
+commands:
+  api:
+    commands:
+      build: { tasks: [echo] }
+    test:
+      needs: [build]
+      commands:
+        .: { tasks: [echo] }
+",
+		)
+		.unwrap();
+
+		assert_eq!(
+			config["commands"]["api"]["test"]["needs"],
+			serde_yaml::from_str::<Value>("[api:build]").unwrap()
+		);
+	}
+
+	#[test]
+	fn resolves_needs_of_command_entries() {
+		let config = resolve(
+			"
+commands:
+  commands:
+    lint: { tasks: [echo] }
+  api:
+    needs: [lint]
+    commands:
+      build: { tasks: [echo] }
+      test: { needs: [build], tasks: [echo] }
+",
+		)
+		.unwrap();
+
+		assert_eq!(
+			config["commands"]["api"]["commands"]["test"]["needs"],
+			serde_yaml::from_str::<Value>("[api:build]").unwrap()
+		);
+	}
+
+	#[test]
+	fn reports_cycles_through_needs_of_command_entries() {
+		let err = resolve(
+			"commands: { commands: { a: { needs: [b], tasks: [echo] }, b: { tasks: ['@a'] } } }",
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"command reference cycle detected: a -> b -> a"
+		);
+	}
+
+	#[test]
+	fn reports_unknown_references() {
+		let err = resolve("commands: { commands: { test: { tasks: ['@missing'] } } }").unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"unknown command 'missing' referenced in 'test'"
+		);
+	}
+
+	#[test]
+	fn reports_unknown_needs() {
+		let err = resolve(
+			"commands: { api: { needs: [missing], commands: { test: { tasks: [echo] } } } }",
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"unknown command 'missing' referenced in 'api (needs)'"
+		);
+	}
+
+	#[test]
+	fn reports_reference_cycles() {
+		let err = resolve(
+			"
+commands:
+  commands:
+    a: { tasks: ['@b'] }
+    b: { tasks: [echo, '@c'] }
+    c: { tasks: ['@a'] }
+",
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"command reference cycle detected: a -> b -> c -> a"
+		);
+	}
+
+	#[test]
+	fn reports_cycles_through_inherited_pre_tasks() {
+		let err = resolve(
+			"
+commands:
+  api:
+    pre: { tasks: ['@setup'] }
+    commands:
+      setup: { tasks: [echo] }
+",
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"command reference cycle detected: api:setup -> api:setup"
+		);
+	}
+
+	#[test]
+	fn rejects_references_in_parallel_tasks() {
+		let err = resolve(
+			"commands: { commands: { a: { tasks: [echo] }, b: { parallel: true, tasks: ['@a'] } } }",
+		)
+		.unwrap_err();
+
+		assert!(
+			err.to_string()
+				.starts_with("invalid task reference in 'b': '@a'")
+		);
+	}
+
+	#[test]
+	fn keeps_shell_tasks_starting_with_an_at_sign() {
+		let config = resolve("commands: { commands: { a: { tasks: ['@echo off'] } } }").unwrap();
+
+		assert_eq!(get_tasks(&config, &["commands", "a"]), ["@echo off"]);
+	}
+}
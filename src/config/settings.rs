@@ -0,0 +1,153 @@
+use std::sync::OnceLock;
+
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::{Mapping, Value};
+
+use crate::{config::constants, util::log::LogLevel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorMode {
+	pub const NAMES: [&str; 3] = ["auto", "always", "never"];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"auto" => Some(ColorMode::Auto),
+			"always" => Some(ColorMode::Always),
+			"never" => Some(ColorMode::Never),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+	pub scope_separator: String,
+	// shell used to run every task, falls back to 'sh' ('cmd' on windows) when unset
+	pub shell: Option<String>,
+	pub color: ColorMode,
+	pub log_level: LogLevel,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Settings {
+			scope_separator: constants::DEFAULT_SCOPE_SEPARATOR.to_string(),
+			shell: None,
+			color: ColorMode::Auto,
+			log_level: LogLevel::Info,
+		}
+	}
+}
+
+impl Settings {
+	// overrides any settings defined in the 'settings' key of a config file
+	pub fn apply_config(&mut self, path: &str, config: &Mapping) -> Result<()> {
+		let mapping = match config.get("settings") {
+			Some(Value::Mapping(mapping)) => mapping,
+			Some(value) => {
+				return Err(eyre!(
+					"{path} - invalid 'settings': {value:?}\n'settings' must be a mapping"
+				));
+			}
+			None => return Ok(()),
+		};
+
+		for (key, value) in mapping {
+			let key = key
+				.as_str()
+				.ok_or_else(|| eyre!("{path} - invalid key: {key:?}\nkey must be a string"))?;
+
+			match key {
+				"scope_separator" => {
+					let separator = get_string(path, key, value)?;
+
+					if separator.is_empty() || separator.chars().any(char::is_whitespace) {
+						return Err(eyre!(
+							"{path} - invalid 'scope_separator': '{separator}'\nseparator must be non empty and contain no whitespace"
+						));
+					}
+
+					// these already have a meaning in config keys, so names using them would be ambiguous
+					if let Some(reserved) = separator
+						.chars()
+						.find(|char| constants::SCOPE_KEY_RESERVED_CHARS.contains(char))
+					{
+						return Err(eyre!(
+							"{path} - invalid 'scope_separator': '{separator}'\n'{reserved}' is reserved for key shorthands, separator can't contain any of: {}",
+							constants::SCOPE_KEY_RESERVED_CHARS
+								.iter()
+								.map(|char| format!("'{char}'"))
+								.collect::<Vec<_>>()
+								.join(", ")
+						));
+					}
+
+					// '.' is also the default command key, which is only unambiguous as the whole separator
+					if separator != "." && separator.contains('.') {
+						return Err(eyre!(
+							"{path} - invalid 'scope_separator': '{separator}'\n'.' is the default command key, it can only be used on its own as the separator"
+						));
+					}
+
+					self.scope_separator = separator.to_string();
+				}
+				"shell" => {
+					self.shell = Some(get_string(path, key, value)?.to_string());
+				}
+				"color" => {
+					let color = get_string(path, key, value)?;
+
+					self.color = ColorMode::from_name(color).ok_or_else(|| {
+						eyre!(
+							"{path} - invalid 'color': '{color}'\nexpected one of: {}",
+							ColorMode::NAMES.join(", ")
+						)
+					})?;
+				}
+				"log_level" => {
+					let log_level = get_string(path, key, value)?;
+
+					self.log_level = LogLevel::from_name(log_level).ok_or_else(|| {
+						eyre!(
+							"{path} - invalid 'log_level': '{log_level}'\nexpected one of: {}",
+							LogLevel::NAMES.join(", ")
+						)
+					})?;
+				}
+				_ => {
+					return Err(eyre!(
+						"{path} - unknown setting: '{key}'\nexpected one of: {}",
+						constants::SETTINGS_KEYS.join(", ")
+					));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn get_string<'a>(path: &str, key: &str, value: &'a Value) -> Result<&'a str> {
+	value
+		.as_str()
+		.ok_or_else(|| eyre!("{path} - invalid '{key}': {value:?}\nvalue must be a string"))
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+// settings can only be set once at startup, before any config is processed
+pub fn init(settings: Settings) -> Result<()> {
+	SETTINGS
+		.set(settings)
+		.map_err(|_| eyre!("settings have already been initialized"))
+}
+
+pub fn get() -> &'static Settings {
+	SETTINGS.get_or_init(Settings::default)
+}
@@ -0,0 +1,131 @@
+use std::{fmt, process::ExitStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskCollectionKind {
+	Pre(usize),
+	Command,
+	Post(usize),
+}
+
+impl fmt::Display for TaskCollectionKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TaskCollectionKind::Pre(i) => write!(f, "pre[{i}]"),
+			TaskCollectionKind::Command => write!(f, "command"),
+			TaskCollectionKind::Post(i) => write!(f, "post[{i}]"),
+		}
+	}
+}
+
+// returned when a spawned task exits unsuccessfully, so the process exit status can mirror it
+#[derive(Debug)]
+pub struct TaskFailure {
+	pub scope_path: String,
+	pub collection: TaskCollectionKind,
+	pub task_index: usize,
+	pub task: String,
+	pub status: ExitStatus,
+}
+
+impl TaskFailure {
+	pub fn exit_code(&self) -> i32 {
+		if let Some(code) = self.status.code() {
+			return code;
+		}
+
+		// follow the shell convention of 128 + signal number for tasks killed by a signal
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::ExitStatusExt;
+
+			if let Some(signal) = self.status.signal() {
+				return 128 + signal;
+			}
+		}
+
+		1
+	}
+
+	fn describe_status(&self) -> String {
+		if let Some(code) = self.status.code() {
+			return format!("exited with code {code}");
+		}
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::ExitStatusExt;
+
+			if let Some(signal) = self.status.signal() {
+				return format!("terminated by signal {signal}");
+			}
+		}
+
+		self.status.to_string()
+	}
+}
+
+impl fmt::Display for TaskFailure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"task failed in '{}' ({} tasks, task {}): '{}' {}",
+			self.scope_path,
+			self.collection,
+			self.task_index,
+			self.task,
+			self.describe_status()
+		)
+	}
+}
+
+impl std::error::Error for TaskFailure {}
+
+// returned when a termination signal was received while a command scope was running
+#[derive(Debug)]
+pub struct Interrupted {
+	pub signal: i32,
+}
+
+impl Interrupted {
+	pub fn exit_code(&self) -> i32 {
+		128 + self.signal
+	}
+}
+
+impl fmt::Display for Interrupted {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "interrupted by signal {}", self.signal)
+	}
+}
+
+impl std::error::Error for Interrupted {}
+
+// returned when a command fanned out across workspaces failed in any of them, after all of them ran
+#[derive(Debug)]
+pub struct FanOutFailure {
+	pub command: String,
+	pub failed_workspaces: Vec<String>,
+	pub workspace_count: usize,
+	pub exit_code: i32,
+}
+
+impl FanOutFailure {
+	pub fn exit_code(&self) -> i32 {
+		self.exit_code
+	}
+}
+
+impl fmt::Display for FanOutFailure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"'{}' failed in {} of {} workspaces: {}",
+			self.command,
+			self.failed_workspaces.len(),
+			self.workspace_count,
+			self.failed_workspaces.join(", ")
+		)
+	}
+}
+
+impl std::error::Error for FanOutFailure {}
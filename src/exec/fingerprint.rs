@@ -0,0 +1,197 @@
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{Result, eyre::eyre};
+use glob::Pattern;
+use sha2::{Digest, Sha256};
+
+use crate::{
+	log_debug,
+	models::command::{CommandScope, WorkingDir},
+};
+
+// everything a command's last successful run depended on: its tasks, working directories and
+// variables as they were resolved, and the contents of its source files
+pub struct Fingerprint {
+	path: Utf8PathBuf,
+	value: String,
+}
+
+impl Fingerprint {
+	pub fn compute(
+		scope_path: &str,
+		command_scope: &CommandScope,
+		state_dir: &Utf8Path,
+	) -> Result<Self> {
+		let base_dir = get_base_dir(command_scope)?;
+
+		let mut hasher = Sha256::new();
+		hash_field(&mut hasher, scope_path);
+		hash_field(
+			&mut hasher,
+			command_scope
+				.working_dir
+				.as_ref()
+				.and_then(WorkingDir::path)
+				.unwrap_or_default(),
+		);
+
+		for task_collection in command_scope.task_collections() {
+			hash_field(
+				&mut hasher,
+				task_collection
+					.working_dir
+					.as_ref()
+					.and_then(WorkingDir::path)
+					.unwrap_or_default(),
+			);
+
+			for task in &task_collection.tasks {
+				hash_field(&mut hasher, task);
+			}
+		}
+
+		let mut variables = command_scope.variables.iter().collect::<Vec<_>>();
+		variables.sort_by_key(|(name, _)| name.as_str());
+
+		for (name, value) in variables {
+			hash_field(&mut hasher, name);
+			hash_field(&mut hasher, serde_yaml::to_string(value)?.as_str());
+		}
+
+		for source in get_source_files(&base_dir, &command_scope.sources)? {
+			let contents = fs::read(&source)
+				.map_err(|err| eyre!("unable to read source file '{source}'\n{err}"))?;
+
+			hash_field(
+				&mut hasher,
+				source.strip_prefix(&base_dir).unwrap_or(&source).as_str(),
+			);
+			hasher.update((contents.len() as u64).to_le_bytes());
+			hasher.update(contents);
+		}
+
+		// each command (and each workspace a command fans out to) has a fingerprint of its own
+		let file_name = format!("{:x}", Sha256::digest(scope_path));
+
+		Ok(Self {
+			path: state_dir.join("fingerprints").join(file_name),
+			value: format!("{:x}", hasher.finalize()),
+		})
+	}
+
+	// a command is up to date when nothing it depends on changed since it last passed, and every
+	// output it declares is still there
+	pub fn is_up_to_date(&self, command_scope: &CommandScope) -> Result<bool> {
+		let Ok(last_value) = fs::read_to_string(&self.path) else {
+			return Ok(false);
+		};
+
+		if last_value.trim() != self.value {
+			log_debug!(
+				"fingerprint changed: {} -> {}",
+				last_value.trim(),
+				self.value
+			);
+
+			return Ok(false);
+		}
+
+		let base_dir = get_base_dir(command_scope)?;
+
+		for output in &command_scope.outputs {
+			if glob::glob(&get_full_pattern(&base_dir, output))
+				.map_err(|err| eyre!("invalid glob pattern in outputs: '{output}'\n{err}"))?
+				.next()
+				.is_none()
+			{
+				log_debug!("output '{output}' is missing");
+
+				return Ok(false);
+			}
+		}
+
+		Ok(true)
+	}
+
+	pub fn save(&self) -> Result<()> {
+		let fingerprints_dir = self.path.parent().unwrap();
+		let state_dir = fingerprints_dir.parent().unwrap();
+
+		if !state_dir.is_dir() {
+			fs::create_dir_all(state_dir)?;
+			// the state is local to each checkout, so it's kept out of version control
+			fs::write(state_dir.join(".gitignore"), "*\n")?;
+		}
+
+		fs::create_dir_all(fingerprints_dir)?;
+		fs::write(&self.path, format!("{}\n", self.value))
+			.map_err(|err| eyre!("unable to save fingerprint '{}'\n{err}", self.path))?;
+
+		Ok(())
+	}
+}
+
+// fields are length prefixed, so moving text from one field to the next changes the fingerprint
+fn hash_field(hasher: &mut Sha256, field: &str) {
+	hasher.update((field.len() as u64).to_le_bytes());
+	hasher.update(field);
+}
+
+// sources and outputs are relative to the directory the command's tasks run in
+fn get_base_dir(command_scope: &CommandScope) -> Result<Utf8PathBuf> {
+	match command_scope
+		.command
+		.working_dir
+		.as_ref()
+		.or(command_scope.working_dir.as_ref())
+		.and_then(WorkingDir::path)
+	{
+		Some(working_dir) => Ok(Utf8PathBuf::from(working_dir)),
+		None => Utf8PathBuf::from_path_buf(std::env::current_dir()?)
+			.map_err(|_| eyre!("unable to process non UTF-8 path")),
+	}
+}
+
+fn get_full_pattern(base_dir: &Utf8Path, pattern: &str) -> String {
+	if Utf8Path::new(pattern).is_absolute() {
+		pattern.to_string()
+	} else {
+		format!("{}/{pattern}", Pattern::escape(base_dir.as_str()))
+	}
+}
+
+// every file the source globs match, with matched directories walked recursively, in sorted order
+fn get_source_files(base_dir: &Utf8Path, sources: &[String]) -> Result<Vec<Utf8PathBuf>> {
+	let mut source_files = Vec::new();
+
+	for source in sources {
+		for entry in glob::glob(&get_full_pattern(base_dir, source))
+			.map_err(|err| eyre!("invalid glob pattern in sources: '{source}'\n{err}"))?
+		{
+			let path = Utf8PathBuf::from_path_buf(entry?)
+				.map_err(|_| eyre!("unable to process non UTF-8 path"))?;
+
+			collect_files(path, &mut source_files)?;
+		}
+	}
+
+	source_files.sort_unstable();
+	source_files.dedup();
+
+	Ok(source_files)
+}
+
+fn collect_files(path: Utf8PathBuf, files: &mut Vec<Utf8PathBuf>) -> Result<()> {
+	if !path.is_dir() {
+		files.push(path);
+
+		return Ok(());
+	}
+
+	for entry in path.read_dir_utf8()? {
+		collect_files(entry?.into_path(), files)?;
+	}
+
+	Ok(())
+}
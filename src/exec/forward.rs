@@ -0,0 +1,133 @@
+use crate::{config::names, models::command::CommandScope, util::template};
+
+pub const ARGS_VARIABLE: &str = "args";
+
+// shell style placeholders, rewritten to the `{{args}}` template placeholder. the quoted form is
+// matched first, as the args are quoted individually when inserted
+const SHELL_ARGS_PLACEHOLDERS: [&str; 2] = ["\"$@\"", "$@"];
+
+// marks where the args given after `--` get inserted: wherever a task asks for them, or appended to
+// the last command task if no task has a placeholder. the args themselves are inserted when the
+// tasks are interpolated. tasks referring to other commands never get them
+pub fn forward_args(command_scope: &mut CommandScope) {
+	let args_placeholder = format!(
+		"{}{ARGS_VARIABLE}{}",
+		template::PLACEHOLDER_START,
+		template::PLACEHOLDER_END
+	);
+
+	let mut placeholder_used = false;
+	for task_collection in command_scope.task_collections_mut() {
+		for task in task_collection.tasks.iter_mut() {
+			if names::get_task_reference(task).is_some() {
+				continue;
+			}
+
+			for placeholder in SHELL_ARGS_PLACEHOLDERS {
+				*task = task.replace(placeholder, &args_placeholder);
+			}
+
+			if template::get_expressions(task).contains(&ARGS_VARIABLE) {
+				placeholder_used = true;
+			}
+		}
+	}
+
+	if !placeholder_used
+		&& !command_scope.forwarded_args.is_empty()
+		&& let Some(last_task) = command_scope
+			.command
+			.tasks
+			.iter_mut()
+			.rev()
+			.find(|task| names::get_task_reference(task).is_none())
+	{
+		last_task.push(' ');
+		last_task.push_str(&args_placeholder);
+	}
+}
+
+pub fn quote_args(args: &[String]) -> String {
+	args.iter()
+		.map(|arg| quote_arg(arg))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+#[cfg(not(windows))]
+pub fn quote_arg(arg: &str) -> String {
+	if !arg.is_empty()
+		&& arg
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "-_=+./:,@%".contains(c))
+	{
+		return arg.to_string();
+	}
+
+	format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+pub fn quote_arg(arg: &str) -> String {
+	if !arg.is_empty() && !arg.contains([' ', '\t', '"', '&', '|', '<', '>', '^']) {
+		return arg.to_string();
+	}
+
+	format!("\"{}\"", arg.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::models::command::TaskCollection;
+
+	fn forward(tasks: &[&str], forwarded_args: &[&str]) -> Vec<String> {
+		let mut command_scope = CommandScope {
+			command: TaskCollection {
+				tasks: tasks.iter().map(|task| task.to_string()).collect(),
+				..Default::default()
+			},
+			forwarded_args: forwarded_args.iter().map(|arg| arg.to_string()).collect(),
+			..Default::default()
+		};
+
+		forward_args(&mut command_scope);
+
+		command_scope.command.tasks
+	}
+
+	#[test]
+	fn appends_args_to_the_last_task() {
+		assert_eq!(
+			forward(&["cargo build", "cargo test", "@lint"], &["--release"]),
+			["cargo build", "cargo test {{args}}", "@lint"]
+		);
+	}
+
+	#[test]
+	fn rewrites_shell_placeholders() {
+		assert_eq!(
+			forward(&["echo \"$@\"", "echo $@ done"], &["a"]),
+			["echo {{args}}", "echo {{args}} done"]
+		);
+	}
+
+	#[test]
+	fn leaves_tasks_as_is_without_args() {
+		assert_eq!(forward(&["cargo test"], &[]), ["cargo test"]);
+	}
+
+	#[cfg(not(windows))]
+	#[test]
+	fn quotes_args_for_the_shell() {
+		assert_eq!(
+			quote_args(&[
+				"--name=api".to_string(),
+				"two words".to_string(),
+				"it's".to_string(),
+				String::new(),
+			]),
+			r"--name=api 'two words' 'it'\''s' ''"
+		);
+	}
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use camino::Utf8Path;
+use color_eyre::{Report, Result, eyre::eyre};
+use serde_yaml::Value;
+
+use crate::{
+	config::constants::{ENV_NAMESPACE, OPTS_NAMESPACE},
+	exec::forward,
+	models::command::{CommandScope, WorkingDir},
+	util::{path as path_util, template},
+};
+
+// resolves placeholder expressions against the variables accumulated for a command scope. the
+// scope variables already hold the innermost definition of each name
+pub struct VariableResolver {
+	variables: HashMap<String, Value>,
+	options: HashMap<String, Value>,
+	forwarded_args: String,
+	resolved: HashMap<String, String>,
+	resolving: Vec<String>,
+}
+
+impl VariableResolver {
+	pub fn new(command_scope: &CommandScope) -> Self {
+		Self {
+			variables: command_scope.variables.clone(),
+			options: command_scope.options.clone(),
+			forwarded_args: forward::quote_args(&command_scope.forwarded_args),
+			resolved: HashMap::new(),
+			resolving: Vec::new(),
+		}
+	}
+
+	pub fn render(&mut self, template: &str) -> Result<String> {
+		template::render(template, |expression| {
+			self.resolve(expression)
+				.map_err(|err| eyre!("unable to interpolate '{template}'\n{err}"))
+		})
+	}
+
+	// expressions are a variable name followed by optional filters, ie `env.PORT | default: 3000`
+	pub fn resolve(&mut self, expression: &str) -> Result<String> {
+		let mut parts = expression.split('|').map(str::trim);
+		let name = parts.next().unwrap();
+
+		let mut value = self.lookup(name)?;
+
+		for filter in parts {
+			let (filter_name, filter_arg) = match filter.split_once(':') {
+				Some((filter_name, filter_arg)) => (filter_name.trim(), Some(filter_arg.trim())),
+				None => (filter, None),
+			};
+
+			match (filter_name, filter_arg) {
+				("default", Some(default)) => {
+					if value.is_none() {
+						value = Some(unquote(default).to_string());
+					}
+				}
+				// renders its value only when the expression is set to something truthy, so options
+				// can be tested, ie `opts.release | if: --release`
+				("if", Some(then)) => {
+					value = Some(if value.as_deref().is_some_and(is_truthy) {
+						unquote(then).to_string()
+					} else {
+						String::new()
+					});
+				}
+				("default" | "if", None) => {
+					return Err(eyre!(
+						"filter '{filter_name}' requires a value in '{expression}', ie '{filter_name}: value'"
+					));
+				}
+				_ => return Err(eyre!("unknown filter '{filter_name}' in '{expression}'")),
+			}
+		}
+
+		value.ok_or_else(|| self.undefined_error(name))
+	}
+
+	fn lookup(&mut self, name: &str) -> Result<Option<String>> {
+		if name == forward::ARGS_VARIABLE {
+			return Ok(Some(self.forwarded_args.clone()));
+		}
+
+		if let Some(env_name) = name.strip_prefix(ENV_NAMESPACE) {
+			return Ok(std::env::var(env_name).ok());
+		}
+
+		if let Some(opt_name) = name.strip_prefix(OPTS_NAMESPACE) {
+			return self
+				.options
+				.get(opt_name)
+				.map(|value| scalar_to_string(name, value))
+				.transpose();
+		}
+
+		if !self.variables.contains_key(name) {
+			return Ok(None);
+		}
+
+		self.resolve_variable(name).map(Some)
+	}
+
+	fn undefined_error(&self, name: &str) -> Report {
+		if let Some(env_name) = name.strip_prefix(ENV_NAMESPACE) {
+			return eyre!(
+				"environment variable '{env_name}' is not set\nset it, or provide a fallback with '{{{{{name} | default: value}}}}'"
+			);
+		}
+
+		if let Some(opt_name) = name.strip_prefix(OPTS_NAMESPACE) {
+			return eyre!(
+				"option '--{opt_name}' was not given\npass it, or provide a fallback with '{{{{{name} | default: value}}}}'"
+			);
+		}
+
+		let mut defined = self.variables.keys().cloned().collect::<Vec<_>>();
+		defined.sort_unstable();
+
+		eyre!(
+			"undefined variable '{name}'\ndefined variables: {}",
+			if defined.is_empty() {
+				"(none)".to_string()
+			} else {
+				defined.join(", ")
+			}
+		)
+	}
+
+	fn resolve_variable(&mut self, name: &str) -> Result<String> {
+		if let Some(value) = self.resolved.get(name) {
+			return Ok(value.clone());
+		}
+
+		if self.resolving.iter().any(|resolving| resolving == name) {
+			return Err(eyre!(
+				"variable '{name}' references itself: {} -> {name}",
+				self.resolving.join(" -> ")
+			));
+		}
+
+		let value = &self.variables[name];
+		let raw_value = scalar_to_string(name, value)?;
+
+		// variable values can reference other variables themselves
+		self.resolving.push(name.to_string());
+		let value = template::render(&raw_value, |expression| self.resolve(expression));
+		self.resolving.pop();
+
+		let value = value?;
+		self.resolved.insert(name.to_string(), value.clone());
+
+		Ok(value)
+	}
+}
+
+fn is_truthy(value: &str) -> bool {
+	!matches!(value, "" | "false" | "0" | "no" | "off")
+}
+
+fn unquote(value: &str) -> &str {
+	for quote in ['"', '\''] {
+		if let Some(unquoted) = value
+			.strip_prefix(quote)
+			.and_then(|value| value.strip_suffix(quote))
+		{
+			return unquoted;
+		}
+	}
+
+	value
+}
+
+fn scalar_to_string(name: &str, value: &Value) -> Result<String> {
+	match value {
+		Value::String(value) => Ok(value.clone()),
+		Value::Bool(value) => Ok(value.to_string()),
+		Value::Number(value) => Ok(value.to_string()),
+		Value::Null => Ok(String::new()),
+		_ => Err(eyre!(
+			"variable '{name}' is invalid type: {value:?}\nvariable value must be a string, number, bool or null"
+		)),
+	}
+}
+
+// substitutes variable placeholders into every task, working directory, source and output of the
+// command scope
+pub fn interpolate_command_scope(command_scope: &mut CommandScope) -> Result<()> {
+	let mut resolver = VariableResolver::new(command_scope);
+
+	if let Some(working_dir) = command_scope.working_dir.as_mut() {
+		interpolate_working_dir(&mut resolver, working_dir)?;
+	}
+
+	for task_collection in command_scope.task_collections_mut() {
+		if let Some(working_dir) = task_collection.working_dir.as_mut() {
+			interpolate_working_dir(&mut resolver, working_dir)?;
+		}
+
+		for task in task_collection.tasks.iter_mut() {
+			*task = resolver.render(task)?;
+		}
+	}
+
+	for path in command_scope
+		.sources
+		.iter_mut()
+		.chain(command_scope.outputs.iter_mut())
+	{
+		*path = resolver.render(path)?;
+	}
+
+	Ok(())
+}
+
+// templated dirs could not be resolved while loading the config, so this is done once their
+// placeholders can be substituted
+fn interpolate_working_dir(
+	resolver: &mut VariableResolver,
+	working_dir: &mut WorkingDir,
+) -> Result<()> {
+	if let WorkingDir::Templated { .. } = working_dir {
+		*working_dir = WorkingDir::Path(render_working_dir(resolver, working_dir)?);
+	}
+
+	Ok(())
+}
+
+// relative results resolve against the rendered directory of the scope the dir was set in. a bare
+// name is a directory of that scope when there is one, and the name of a workspace otherwise
+fn render_working_dir(resolver: &mut VariableResolver, working_dir: &WorkingDir) -> Result<String> {
+	let (base_dir, template) = match working_dir {
+		WorkingDir::Path(path) => return Ok(path.clone()),
+		WorkingDir::Templated { base, template } => (render_working_dir(resolver, base)?, template),
+	};
+
+	let rendered = resolver.render(template)?;
+
+	if rendered.starts_with("ws:") {
+		return Ok(rendered);
+	}
+
+	if let Some(workspace_dir) = base_dir.strip_prefix("ws:") {
+		// the scope's workspace isn't known yet, so the path is carried along with it
+		return Ok(if path_util::is_relative_path(&rendered) {
+			format!("ws:{workspace_dir}/{rendered}")
+		} else {
+			format!("ws:{rendered}")
+		});
+	}
+
+	let path = if path_util::looks_like_path(&rendered) {
+		rendered.clone()
+	} else if !rendered.is_empty() && Utf8Path::new(&base_dir).join(&rendered).is_dir() {
+		format!("./{rendered}")
+	} else {
+		return Ok(format!("ws:{rendered}"));
+	};
+
+	let path = path_util::try_get_path(&path, Some(base_dir))?.unwrap();
+
+	if !path.is_dir() {
+		return Err(eyre!(
+			"invalid working directory path: {path}\npath must be to a directory"
+		));
+	}
+
+	Ok(path.into_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn resolver(variables: &[(&str, &str)], options: &[(&str, Value)]) -> VariableResolver {
+		VariableResolver::new(&CommandScope {
+			variables: variables
+				.iter()
+				.map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+				.collect(),
+			options: options
+				.iter()
+				.map(|(name, value)| (name.to_string(), value.clone()))
+				.collect(),
+			..Default::default()
+		})
+	}
+
+	#[test]
+	fn resolves_variables_that_reference_others() {
+		let mut resolver = resolver(&[("name", "api"), ("dir", "./{{name}}")], &[]);
+
+		assert_eq!(resolver.render("cd {{ dir }}").unwrap(), "cd ./api");
+	}
+
+	#[test]
+	fn default_filter_applies_only_to_missing_values() {
+		let mut resolver = resolver(&[("port", "8080")], &[]);
+
+		assert_eq!(resolver.resolve("port | default: 3000").unwrap(), "8080");
+		assert_eq!(
+			resolver
+				.resolve("opts.host | default: 'localhost'")
+				.unwrap(),
+			"localhost"
+		);
+	}
+
+	#[test]
+	fn if_filter_renders_for_truthy_values() {
+		let mut resolver = resolver(
+			&[],
+			&[
+				("release", Value::Bool(true)),
+				("verbose", Value::Bool(false)),
+			],
+		);
+
+		assert_eq!(
+			resolver.resolve("opts.release | if: --release").unwrap(),
+			"--release"
+		);
+		assert_eq!(
+			resolver.resolve("opts.verbose | if: --verbose").unwrap(),
+			""
+		);
+		assert_eq!(
+			resolver.resolve("opts.missing | if: --missing").unwrap(),
+			""
+		);
+	}
+
+	#[test]
+	fn filters_apply_in_order() {
+		let mut resolver = resolver(&[], &[]);
+
+		assert_eq!(
+			resolver.resolve("opts.x | default: no | if: yes").unwrap(),
+			""
+		);
+		assert_eq!(
+			resolver.resolve("opts.x | default: on | if: yes").unwrap(),
+			"yes"
+		);
+	}
+
+	#[test]
+	fn rejects_filters_without_values() {
+		let err = resolver(&[], &[]).resolve("x | default").unwrap_err();
+
+		assert!(
+			err.to_string()
+				.contains("filter 'default' requires a value")
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_filters() {
+		let err = resolver(&[("x", "1")], &[])
+			.resolve("x | upper")
+			.unwrap_err();
+
+		assert!(err.to_string().contains("unknown filter 'upper'"));
+	}
+
+	#[test]
+	fn reports_undefined_variables() {
+		let err = resolver(&[("b", "1"), ("a", "2")], &[])
+			.resolve("c")
+			.unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"undefined variable 'c'\ndefined variables: a, b"
+		);
+	}
+
+	#[test]
+	fn reports_self_referencing_variables() {
+		let err = resolver(&[("a", "{{b}}"), ("b", "{{a}}")], &[])
+			.render("{{a}}")
+			.unwrap_err();
+
+		assert!(format!("{err:?}").contains("references itself: a -> b -> a"));
+	}
+
+	fn templated_dir(base: WorkingDir, template: &str) -> WorkingDir {
+		WorkingDir::Templated {
+			base: Box::new(base),
+			template: template.to_string(),
+		}
+	}
+
+	#[test]
+	fn renders_templated_dirs_against_their_rendered_base() {
+		let root = std::env::temp_dir().join(format!("pm-interpolate-{}", std::process::id()));
+		std::fs::create_dir_all(root.join("x").join("y")).unwrap();
+		let root = path_util::try_get_path(root.to_str().unwrap(), None)
+			.unwrap()
+			.unwrap();
+
+		let working_dir = templated_dir(
+			templated_dir(WorkingDir::Path(root.to_string()), "{{a}}"),
+			"{{b}}",
+		);
+		let rendered =
+			render_working_dir(&mut resolver(&[("a", "x"), ("b", "y")], &[]), &working_dir);
+
+		std::fs::remove_dir_all(&root).unwrap();
+		assert_eq!(rendered.unwrap(), root.join("x").join("y").as_str());
+	}
+
+	#[test]
+	fn renders_templated_dirs_in_workspaces_as_references() {
+		let mut resolver = resolver(&[("sub", "./src"), ("name", "web")], &[]);
+		let base = WorkingDir::Path("ws:api".to_string());
+
+		assert_eq!(
+			render_working_dir(&mut resolver, &templated_dir(base.clone(), "{{sub}}")).unwrap(),
+			"ws:api/./src"
+		);
+		assert_eq!(
+			render_working_dir(&mut resolver, &templated_dir(base, "{{name}}")).unwrap(),
+			"ws:web"
+		);
+	}
+}
@@ -0,0 +1,9 @@
+pub mod error;
+pub mod fingerprint;
+pub mod forward;
+pub mod interpolate;
+pub mod output;
+pub mod process;
+pub mod runner;
+pub mod signals;
+pub mod workspace;
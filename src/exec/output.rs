@@ -0,0 +1,68 @@
+use std::{
+	io::{self, BufRead, BufReader, IsTerminal, Read, Write},
+	thread::{self, JoinHandle},
+};
+
+use crate::config::settings::{self, ColorMode};
+
+const PREFIX_COLORS: [&str; 6] = ["36", "33", "32", "35", "34", "31"];
+
+const MAX_TASK_NAME_LEN: usize = 24;
+
+// shortens a task command line so it can be used as a readable output prefix
+pub fn task_name(task: &str) -> String {
+	if task.chars().count() <= MAX_TASK_NAME_LEN {
+		return task.to_string();
+	}
+
+	let truncated = task.chars().take(MAX_TASK_NAME_LEN - 3).collect::<String>();
+	format!("{}...", truncated.trim_end())
+}
+
+pub fn task_prefix(task_index: usize, task_name: &str, width: usize) -> String {
+	let label = format!("[{task_name}]");
+	let label = format!("{label:<width$}", width = width + 2);
+
+	let use_color = match settings::get().color {
+		ColorMode::Always => true,
+		ColorMode::Never => false,
+		ColorMode::Auto => io::stdout().is_terminal(),
+	};
+
+	if use_color {
+		let color = PREFIX_COLORS[task_index % PREFIX_COLORS.len()];
+		format!("\x1b[{color}m{label}\x1b[0m")
+	} else {
+		label
+	}
+}
+
+// forwards every line read from a child pipe to our own stdout/stderr with the task prefix attached
+pub fn forward_lines<R: Read + Send + 'static>(
+	reader: R,
+	prefix: String,
+	to_stderr: bool,
+) -> JoinHandle<()> {
+	thread::spawn(move || {
+		let mut reader = BufReader::new(reader);
+		let mut buf = Vec::new();
+
+		loop {
+			buf.clear();
+			match reader.read_until(b'\n', &mut buf) {
+				Ok(0) | Err(_) => break,
+				Ok(_) => {
+					let line = String::from_utf8_lossy(&buf);
+					let line = line.trim_end_matches(['\n', '\r']);
+
+					// lock per line so output from different tasks never interleaves mid-line
+					if to_stderr {
+						let _ = writeln!(io::stderr().lock(), "{prefix} {line}");
+					} else {
+						let _ = writeln!(io::stdout().lock(), "{prefix} {line}");
+					}
+				}
+			}
+		}
+	})
+}
@@ -0,0 +1,205 @@
+use std::{
+	process::{Child, Command, ExitStatus},
+	thread,
+	time::{Duration, Instant},
+};
+
+use camino::Utf8Path;
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{config::settings, exec::signals};
+
+pub const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+pub fn build_shell_command(task: &str, working_dir: Option<&str>) -> Command {
+	let mut command = if let Some(shell) = &settings::get().shell {
+		let mut command = Command::new(shell);
+		command.args([shell_command_flag(shell), task]);
+		command
+	} else {
+		default_shell_command(task)
+	};
+
+	if let Some(working_dir) = working_dir {
+		command.current_dir(working_dir);
+	}
+
+	command
+}
+
+#[cfg(windows)]
+fn default_shell_command(task: &str) -> Command {
+	let mut command = Command::new("cmd");
+	command.args(["/C", task]);
+	command
+}
+
+#[cfg(not(windows))]
+fn default_shell_command(task: &str) -> Command {
+	let mut command = Command::new("sh");
+	command.args(["-c", task]);
+	command
+}
+
+// cmd takes the command line with '/C', every other shell is assumed to accept '-c'
+fn shell_command_flag(shell: &str) -> &'static str {
+	let shell_name = Utf8Path::new(shell)
+		.file_stem()
+		.unwrap_or(shell)
+		.to_ascii_lowercase();
+
+	if shell_name == "cmd" { "/C" } else { "-c" }
+}
+
+// places the spawned process in its own process group, so it and all of its descendants can be
+// terminated together
+pub fn isolate_process_group(command: &mut Command) -> &mut Command {
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+
+		command.process_group(0);
+	}
+
+	#[cfg(windows)]
+	{
+		use std::os::windows::process::CommandExt;
+
+		const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+		command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+	}
+
+	command
+}
+
+pub fn run_task(task: &str, working_dir: Option<&str>) -> Result<ExitStatus> {
+	let mut command = build_shell_command(task, working_dir);
+	isolate_process_group(&mut command);
+
+	// interactive tasks need to own the terminal, which also makes ctrl-c go straight to them
+	#[cfg(unix)]
+	let foreground = terminal::is_foreground();
+	#[cfg(unix)]
+	if foreground {
+		terminal::hand_to_child(&mut command);
+	}
+
+	let mut child = command
+		.spawn()
+		.map_err(|err| eyre!("unable to spawn task '{task}'\n{err}"))?;
+	let _tracked_process_group = signals::track_process_group(child.id());
+
+	let status = child
+		.wait()
+		.map_err(|err| eyre!("unable to wait on task '{task}'\n{err}"));
+
+	#[cfg(unix)]
+	if foreground {
+		terminal::reclaim();
+	}
+
+	status
+}
+
+// asks the process tree of a child spawned with `isolate_process_group` to terminate, escalating to a
+// forced kill if it is still running once the grace period has passed
+pub fn terminate_process_tree(child: &mut Child, grace_period: Duration) -> Result<ExitStatus> {
+	if let Some(status) = child.try_wait()? {
+		// the group leader has exited, but descendants may still be holding on
+		kill_process_group(child.id(), true);
+		return Ok(status);
+	}
+
+	kill_process_group(child.id(), false);
+
+	let deadline = Instant::now() + grace_period;
+	while Instant::now() < deadline {
+		if let Some(status) = child.try_wait()? {
+			kill_process_group(child.id(), true);
+			return Ok(status);
+		}
+
+		thread::sleep(Duration::from_millis(50));
+	}
+
+	kill_process_group(child.id(), true);
+
+	Ok(child.wait()?)
+}
+
+#[cfg(unix)]
+fn kill_process_group(pgid: u32, force: bool) {
+	signal_process_group(pgid, if force { libc::SIGKILL } else { libc::SIGTERM });
+}
+
+#[cfg(unix)]
+pub fn signal_process_group(pgid: u32, signal: i32) {
+	// a negative pid targets every process in the group
+	unsafe {
+		libc::kill(-(pgid as libc::pid_t), signal);
+	}
+}
+
+#[cfg(unix)]
+pub fn process_group_exists(pgid: u32) -> bool {
+	unsafe { libc::kill(-(pgid as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(windows)]
+pub fn process_group_exists(_pgid: u32) -> bool {
+	false
+}
+
+#[cfg(windows)]
+fn kill_process_group(pid: u32, _force: bool) {
+	// windows has no graceful group signal for console-less children, so always kill the whole tree
+	let _ = Command::new("taskkill")
+		.args(["/T", "/F", "/PID", &pid.to_string()])
+		.output();
+}
+
+#[cfg(unix)]
+mod terminal {
+	use std::{os::unix::process::CommandExt, process::Command};
+
+	pub fn is_foreground() -> bool {
+		unsafe {
+			libc::isatty(libc::STDIN_FILENO) == 1
+				&& libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+		}
+	}
+
+	pub fn hand_to_child(command: &mut Command) {
+		unsafe {
+			command.pre_exec(|| {
+				libc::setpgid(0, 0);
+				set_foreground_process_group(libc::getpid());
+
+				Ok(())
+			});
+		}
+	}
+
+	pub fn reclaim() {
+		unsafe {
+			set_foreground_process_group(libc::getpgrp());
+		}
+	}
+
+	// SIGTTOU has to be blocked while changing the foreground group from outside of it, otherwise the
+	// caller gets stopped
+	unsafe fn set_foreground_process_group(pgid: libc::pid_t) {
+		unsafe {
+			let mut mask: libc::sigset_t = std::mem::zeroed();
+			let mut prev_mask: libc::sigset_t = std::mem::zeroed();
+
+			libc::sigemptyset(&mut mask);
+			libc::sigaddset(&mut mask, libc::SIGTTOU);
+			libc::pthread_sigmask(libc::SIG_BLOCK, &mask, &mut prev_mask);
+
+			libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+
+			libc::pthread_sigmask(libc::SIG_SETMASK, &prev_mask, std::ptr::null_mut());
+		}
+	}
+}
@@ -0,0 +1,400 @@
+use std::{
+	process::{Child, ExitStatus, Stdio},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+use color_eyre::{Result, eyre::eyre};
+
+use crate::{
+	config::names,
+	exec::{
+		error::{TaskCollectionKind, TaskFailure},
+		output, process,
+		signals::{self, TrackedProcessGroup},
+	},
+	log_info, log_warn,
+	models::command::{CommandScope, TaskCollection, WorkingDir},
+};
+
+// how often the members of a parallel group are checked on
+const PARALLEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct RunningTask {
+	task_index: usize,
+	task: String,
+	child: Child,
+	forwarders: [JoinHandle<()>; 2],
+	_tracked_process_group: TrackedProcessGroup,
+}
+
+// background tasks stay alive for as long as this is held, and are terminated along with their
+// process trees when it is dropped (including when the foreground tasks fail)
+struct BackgroundTasks {
+	scope_path: String,
+	tasks: Vec<(TaskCollectionKind, RunningTask)>,
+}
+
+impl Drop for BackgroundTasks {
+	fn drop(&mut self) {
+		for (collection, running_task) in self.tasks.iter_mut().rev() {
+			let RunningTask { task, child, .. } = running_task;
+
+			log_info!(
+				"{} ({collection}) - stopping background task: {task}",
+				self.scope_path
+			);
+
+			if let Err(err) =
+				process::terminate_process_tree(child, process::TERMINATE_GRACE_PERIOD)
+			{
+				log_warn!(
+					"WARNING - unable to stop background task '{task}' in '{}'\n{err}",
+					self.scope_path
+				);
+			}
+		}
+
+		for (_, running_task) in self.tasks.drain(..) {
+			for forwarder in running_task.forwarders {
+				let _ = forwarder.join();
+			}
+		}
+	}
+}
+
+pub struct RunContext<'a> {
+	// with an output prefix every task's output is prefixed and none of them are given the
+	// terminal, so the scope can run alongside others
+	pub output_prefix: Option<&'a str>,
+	// runs the command a task refers to by name, which needs the config the runner doesn't have
+	pub run_reference: &'a dyn Fn(&str) -> Result<()>,
+}
+
+pub fn run_command_scope(
+	scope_path: &str,
+	command_scope: &CommandScope,
+	context: &RunContext,
+) -> Result<()> {
+	let working_dir = command_scope
+		.working_dir
+		.as_ref()
+		.and_then(WorkingDir::path);
+
+	let mut background_tasks = BackgroundTasks {
+		scope_path: scope_path.to_string(),
+		tasks: Vec::new(),
+	};
+
+	if let Some(pre_tasks) = &command_scope.pre_tasks {
+		for (i, task_collection) in pre_tasks.iter().enumerate() {
+			let collection = TaskCollectionKind::Pre(i);
+
+			if task_collection.background {
+				for running_task in start_background_tasks(
+					scope_path,
+					collection,
+					task_collection,
+					working_dir,
+					context,
+				)? {
+					background_tasks.tasks.push((collection, running_task));
+				}
+
+				continue;
+			}
+
+			run_task_collection(
+				scope_path,
+				collection,
+				task_collection,
+				working_dir,
+				context,
+			)?;
+		}
+	}
+
+	run_task_collection(
+		scope_path,
+		TaskCollectionKind::Command,
+		&command_scope.command,
+		working_dir,
+		context,
+	)?;
+
+	if let Some(post_tasks) = &command_scope.post_tasks {
+		for (i, task_collection) in post_tasks.iter().enumerate() {
+			run_task_collection(
+				scope_path,
+				TaskCollectionKind::Post(i),
+				task_collection,
+				working_dir,
+				context,
+			)?;
+		}
+	}
+
+	drop(background_tasks);
+
+	Ok(())
+}
+
+// a collection's own directory takes precedence over the scope's
+fn resolve_working_dir<'a>(
+	task_collection: &'a TaskCollection,
+	scope_working_dir: Option<&'a str>,
+) -> Option<&'a str> {
+	task_collection
+		.working_dir
+		.as_ref()
+		.and_then(WorkingDir::path)
+		.or(scope_working_dir)
+}
+
+fn run_task_collection(
+	scope_path: &str,
+	collection: TaskCollectionKind,
+	task_collection: &TaskCollection,
+	scope_working_dir: Option<&str>,
+	context: &RunContext,
+) -> Result<()> {
+	let working_dir = resolve_working_dir(task_collection, scope_working_dir);
+
+	if task_collection.parallel {
+		return run_parallel_tasks(
+			scope_path,
+			collection,
+			task_collection,
+			working_dir,
+			context,
+		);
+	}
+
+	for (task_index, task) in task_collection.tasks.iter().enumerate() {
+		signals::check_interrupted()?;
+
+		if let Some(command) = names::get_task_reference(task) {
+			log_info!("{scope_path} ({collection}) - running command: {command}");
+
+			(context.run_reference)(command)?;
+			continue;
+		}
+
+		log_info!("{scope_path} ({collection}) - running task: {task}");
+
+		let status = match context.output_prefix {
+			Some(output_prefix) => run_prefixed_task(task_index, task, working_dir, output_prefix)?,
+			None => process::run_task(task, working_dir)?,
+		};
+
+		if !status.success() {
+			return Err(TaskFailure {
+				scope_path: scope_path.to_string(),
+				collection,
+				task_index,
+				task: task.clone(),
+				status,
+			}
+			.into());
+		}
+	}
+
+	Ok(())
+}
+
+// tasks are prefixed with their own name, after the prefix of the whole scope when it has one
+fn spawn_prefixed_tasks(
+	task_collection: &TaskCollection,
+	working_dir: Option<&str>,
+	output_prefix: Option<&str>,
+) -> Result<Vec<RunningTask>> {
+	signals::check_interrupted()?;
+
+	let task_names = task_collection
+		.tasks
+		.iter()
+		.map(|task| output::task_name(task))
+		.collect::<Vec<_>>();
+	let prefix_width = task_names
+		.iter()
+		.map(|name| name.chars().count())
+		.max()
+		.unwrap_or(0);
+
+	let mut running_tasks = Vec::new();
+	for (task_index, task) in task_collection.tasks.iter().enumerate() {
+		let prefix = output::task_prefix(task_index, &task_names[task_index], prefix_width);
+		let prefix = match output_prefix {
+			Some(output_prefix) => format!("{output_prefix} {prefix}"),
+			None => prefix,
+		};
+
+		match spawn_prefixed_task(task_index, task, working_dir, prefix) {
+			Ok(running_task) => running_tasks.push(running_task),
+			Err(err) => {
+				// tasks that did start would otherwise be left running on their own
+				stop_running_tasks(running_tasks);
+				return Err(err);
+			}
+		}
+	}
+
+	Ok(running_tasks)
+}
+
+// terminates the process trees of tasks that are still running, and waits for their output
+fn stop_running_tasks(running_tasks: Vec<RunningTask>) {
+	for mut running_task in running_tasks {
+		if let Err(err) = process::terminate_process_tree(
+			&mut running_task.child,
+			process::TERMINATE_GRACE_PERIOD,
+		) {
+			log_warn!(
+				"WARNING - unable to stop task '{}'\n{err}",
+				running_task.task
+			);
+		}
+
+		for forwarder in running_task.forwarders {
+			let _ = forwarder.join();
+		}
+	}
+}
+
+fn spawn_prefixed_task(
+	task_index: usize,
+	task: &str,
+	working_dir: Option<&str>,
+	prefix: String,
+) -> Result<RunningTask> {
+	let mut command = process::build_shell_command(task, working_dir);
+	let mut child = process::isolate_process_group(&mut command)
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|err| eyre!("unable to spawn task '{task}'\n{err}"))?;
+	let tracked_process_group = signals::track_process_group(child.id());
+
+	let forwarders = [
+		output::forward_lines(child.stdout.take().unwrap(), prefix.clone(), false),
+		output::forward_lines(child.stderr.take().unwrap(), prefix, true),
+	];
+
+	Ok(RunningTask {
+		task_index,
+		task: task.to_string(),
+		child,
+		forwarders,
+		_tracked_process_group: tracked_process_group,
+	})
+}
+
+// runs a single task with its output prefixed, for scopes running alongside others that can't
+// share the terminal
+fn run_prefixed_task(
+	task_index: usize,
+	task: &str,
+	working_dir: Option<&str>,
+	output_prefix: &str,
+) -> Result<ExitStatus> {
+	let mut running_task =
+		spawn_prefixed_task(task_index, task, working_dir, output_prefix.to_string())?;
+
+	let status = running_task
+		.child
+		.wait()
+		.map_err(|err| eyre!("unable to wait on task '{task}'\n{err}"))?;
+
+	for forwarder in running_task.forwarders {
+		let _ = forwarder.join();
+	}
+
+	Ok(status)
+}
+
+fn run_parallel_tasks(
+	scope_path: &str,
+	collection: TaskCollectionKind,
+	task_collection: &TaskCollection,
+	working_dir: Option<&str>,
+	context: &RunContext,
+) -> Result<()> {
+	log_info!(
+		"{scope_path} ({collection}) - running {} tasks in parallel",
+		task_collection.tasks.len()
+	);
+
+	let mut running_tasks =
+		spawn_prefixed_tasks(task_collection, working_dir, context.output_prefix)?;
+
+	// the first member to fail stops the rest of the group, which could otherwise run forever
+	while !running_tasks.is_empty() {
+		let finished_task = running_tasks
+			.iter_mut()
+			.enumerate()
+			.find_map(|(i, running_task)| {
+				running_task
+					.child
+					.try_wait()
+					.map_err(|err| eyre!("unable to wait on task '{}'\n{err}", running_task.task))
+					.transpose()
+					.map(|status| (i, status))
+			});
+
+		let Some((i, status)) = finished_task else {
+			thread::sleep(PARALLEL_POLL_INTERVAL);
+			continue;
+		};
+
+		let running_task = running_tasks.remove(i);
+		for forwarder in running_task.forwarders {
+			let _ = forwarder.join();
+		}
+
+		let status = match status {
+			Ok(status) if status.success() => continue,
+			Ok(status) => status,
+			Err(err) => {
+				stop_running_tasks(running_tasks);
+				return Err(err);
+			}
+		};
+
+		for running_task in &running_tasks {
+			log_info!(
+				"{scope_path} ({collection}) - stopping parallel task: {}",
+				running_task.task
+			);
+		}
+		stop_running_tasks(running_tasks);
+
+		return Err(TaskFailure {
+			scope_path: scope_path.to_string(),
+			collection,
+			task_index: running_task.task_index,
+			task: running_task.task,
+			status,
+		}
+		.into());
+	}
+
+	Ok(())
+}
+
+fn start_background_tasks(
+	scope_path: &str,
+	collection: TaskCollectionKind,
+	task_collection: &TaskCollection,
+	scope_working_dir: Option<&str>,
+	context: &RunContext,
+) -> Result<Vec<RunningTask>> {
+	let working_dir = resolve_working_dir(task_collection, scope_working_dir);
+
+	log_info!(
+		"{scope_path} ({collection}) - starting {} background tasks",
+		task_collection.tasks.len()
+	);
+
+	spawn_prefixed_tasks(task_collection, working_dir, context.output_prefix)
+}
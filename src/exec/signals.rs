@@ -0,0 +1,162 @@
+use std::{
+	sync::{
+		Mutex, OnceLock,
+		atomic::{AtomicBool, AtomicI32, Ordering},
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+use color_eyre::Result;
+
+use crate::exec::{error::Interrupted, process};
+
+// process group ids of every child currently spawned, so signals can be forwarded to them
+static PROCESS_GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+// the first termination signal received, or 0 if none has been received
+static INTERRUPT_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+static INTERRUPT_DEADLINE: OnceLock<Instant> = OnceLock::new();
+
+static KILLED: AtomicBool = AtomicBool::new(false);
+
+// keeps a spawned process group registered for signal forwarding until dropped
+#[derive(Debug)]
+pub struct TrackedProcessGroup(u32);
+
+impl Drop for TrackedProcessGroup {
+	fn drop(&mut self) {
+		// once interrupted, the group stays registered so any descendants left behind by the task
+		// are still cleaned up before exiting
+		if interrupt_signal().is_some() {
+			return;
+		}
+
+		let mut process_groups = PROCESS_GROUPS.lock().unwrap_or_else(|err| err.into_inner());
+		process_groups.retain(|pgid| *pgid != self.0);
+	}
+}
+
+pub fn track_process_group(pgid: u32) -> TrackedProcessGroup {
+	PROCESS_GROUPS
+		.lock()
+		.unwrap_or_else(|err| err.into_inner())
+		.push(pgid);
+
+	TrackedProcessGroup(pgid)
+}
+
+pub fn interrupt_signal() -> Option<i32> {
+	match INTERRUPT_SIGNAL.load(Ordering::SeqCst) {
+		0 => None,
+		signal => Some(signal),
+	}
+}
+
+// errors once a termination signal has been received, so no new tasks get started
+pub fn check_interrupted() -> Result<()> {
+	if let Some(signal) = interrupt_signal() {
+		return Err(Interrupted { signal }.into());
+	}
+
+	Ok(())
+}
+
+// waits for every process group that received a forwarded signal to exit, killing whatever is
+// left once the grace period is over
+pub fn finish_interrupt() {
+	let Some(deadline) = INTERRUPT_DEADLINE.get() else {
+		return;
+	};
+
+	if !wait_for_process_groups(*deadline) {
+		kill_process_groups("tasks still running after grace period");
+	}
+}
+
+fn tracked_process_groups() -> Vec<u32> {
+	PROCESS_GROUPS
+		.lock()
+		.unwrap_or_else(|err| err.into_inner())
+		.clone()
+}
+
+fn wait_for_process_groups(deadline: Instant) -> bool {
+	while Instant::now() < deadline {
+		if tracked_process_groups()
+			.into_iter()
+			.all(|pgid| !process::process_group_exists(pgid))
+		{
+			return true;
+		}
+
+		thread::sleep(Duration::from_millis(50));
+	}
+
+	false
+}
+
+#[cfg(unix)]
+fn kill_process_groups(reason: &str) {
+	if KILLED.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	eprintln!("{reason}, killing all tasks");
+
+	for pgid in tracked_process_groups() {
+		process::signal_process_group(pgid, libc::SIGKILL);
+	}
+}
+
+#[cfg(not(unix))]
+fn kill_process_groups(_reason: &str) {}
+
+#[cfg(unix)]
+pub fn install_handlers() -> Result<()> {
+	use signal_hook::{
+		consts::{SIGINT, SIGTERM},
+		iterator::Signals,
+	};
+
+	let mut signals = Signals::new([SIGINT, SIGTERM])?;
+
+	thread::spawn(move || {
+		for signal in signals.forever() {
+			if INTERRUPT_SIGNAL
+				.compare_exchange(0, signal, Ordering::SeqCst, Ordering::SeqCst)
+				.is_err()
+			{
+				// a repeated signal skips the grace period
+				kill_process_groups(format!("received signal {signal} again").as_str());
+				std::process::exit(128 + signal);
+			}
+
+			eprintln!(
+				"received signal {signal}, stopping all tasks (send again to kill immediately)"
+			);
+
+			let deadline = *INTERRUPT_DEADLINE
+				.get_or_init(|| Instant::now() + process::TERMINATE_GRACE_PERIOD);
+
+			for pgid in tracked_process_groups() {
+				process::signal_process_group(pgid, signal);
+			}
+
+			// backstop for when the main thread is stuck waiting on a task that ignores the signal
+			thread::spawn(move || {
+				if !wait_for_process_groups(deadline) {
+					kill_process_groups("tasks still running after grace period");
+				}
+			});
+		}
+	});
+
+	Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install_handlers() -> Result<()> {
+	Ok(())
+}
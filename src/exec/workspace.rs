@@ -0,0 +1,126 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{Result, eyre::eyre};
+use serde_yaml::Mapping;
+
+use crate::models::{
+	command::{CommandScope, WorkingDir},
+	workspace::{self, Workspace},
+};
+
+// 'in' values naming a workspace are kept as 'ws:<name>' references while loading, as workspaces
+// are only all known once every config file has been merged and auto captured. templated values
+// are only resolved once they have been interpolated
+pub fn resolve_workspace_dirs(
+	scope_path: &str,
+	command_scope: &mut CommandScope,
+	config: &Mapping,
+) -> Result<()> {
+	let workspaces = workspace::get_workspaces(config);
+
+	if let Some(working_dir) = command_scope.working_dir.as_mut() {
+		resolve_workspace_dir(scope_path, &workspaces, working_dir)?;
+	}
+
+	for task_collection in command_scope.task_collections_mut() {
+		if let Some(working_dir) = task_collection.working_dir.as_mut() {
+			resolve_workspace_dir(scope_path, &workspaces, working_dir)?;
+		}
+	}
+
+	Ok(())
+}
+
+// a command runs in the innermost workspace containing its working directory, whose variables are
+// made available to it without overriding anything the command's scopes define
+pub fn inject_workspace_variables(
+	command_scope: &mut CommandScope,
+	config: &Mapping,
+) -> Result<()> {
+	let workspaces = workspace::get_workspaces(config);
+
+	let working_dir = match command_scope
+		.command
+		.working_dir
+		.as_ref()
+		.or(command_scope.working_dir.as_ref())
+	{
+		// the workspace of a templated directory can't be known before interpolating
+		Some(WorkingDir::Templated { .. }) => return Ok(()),
+		Some(WorkingDir::Path(working_dir)) => Utf8PathBuf::from(working_dir),
+		None => Utf8PathBuf::from_path_buf(dunce::canonicalize(std::env::current_dir()?)?)
+			.map_err(|_| eyre!("unable to process non UTF-8 path"))?,
+	};
+
+	let Some(containing_workspace) =
+		workspace::get_containing_workspaces(&workspaces, &working_dir)
+			.into_iter()
+			.next()
+	else {
+		return Ok(());
+	};
+
+	for (name, value) in &containing_workspace.variables {
+		command_scope
+			.variables
+			.entry(name.clone())
+			.or_insert_with(|| value.clone());
+	}
+
+	Ok(())
+}
+
+fn resolve_workspace_dir(
+	scope_path: &str,
+	workspaces: &[Workspace],
+	working_dir: &mut WorkingDir,
+) -> Result<()> {
+	let working_dir = match working_dir {
+		WorkingDir::Path(working_dir) => working_dir,
+		// the directory a templated one resolves against can be resolved already, so a name it
+		// renders to can be looked for in the workspace
+		WorkingDir::Templated { base, .. } => {
+			return resolve_workspace_dir(scope_path, workspaces, base);
+		}
+	};
+
+	let Some(reference) = working_dir.strip_prefix("ws:") else {
+		return Ok(());
+	};
+
+	// relative paths inside a workspace scope are carried along as 'ws:<name>/<path>'
+	let (name, rest) = reference.split_once(['/', '\\']).unwrap_or((reference, ""));
+
+	let Some(workspace) = workspaces.iter().find(|workspace| workspace.name == name) else {
+		let mut known_workspaces = workspaces
+			.iter()
+			.map(|workspace| workspace.name.as_str())
+			.collect::<Vec<_>>();
+		known_workspaces.sort_unstable();
+
+		return Err(eyre!(
+			"unknown workspace '{name}' used as working directory in '{scope_path}'\n{}",
+			if known_workspaces.is_empty() {
+				"no workspaces are defined".to_string()
+			} else {
+				format!("known workspaces: {}", known_workspaces.join(", "))
+			}
+		));
+	};
+
+	let path = dunce::canonicalize(Utf8Path::new(&workspace.path).join(rest))
+		.map_err(|err| eyre!("unable to resolve working directory '{working_dir}'\n{err}"))?;
+
+	if !path.is_dir() {
+		return Err(eyre!(
+			"invalid working directory path: {}\npath must be to a directory",
+			path.display()
+		));
+	}
+
+	*working_dir = path
+		.into_os_string()
+		.into_string()
+		.map_err(|_| eyre!("unable to process non UTF-8 path"))?;
+
+	Ok(())
+}
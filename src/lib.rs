@@ -1,4 +1,5 @@
 pub mod config;
+pub mod exec;
 pub mod program;
 pub mod util;
 pub mod models;
\ No newline at end of file
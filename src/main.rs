@@ -3,20 +3,77 @@ use color_eyre::{
 	eyre::{Report, eyre},
 };
 use project_manager::{
-	config::parse,
+	config::{discover, parse},
+	exec::{
+		error::{FanOutFailure, Interrupted, TaskFailure},
+		signals,
+	},
 	program::{args::ArgToken, *},
 };
 
 fn main() -> Result<()> {
 	color_eyre::install()?;
+	signals::install_handlers()?;
 
+	let result = run();
+
+	// an interrupt takes precedence over however the interrupted tasks happened to exit
+	if let Some(signal) = signals::interrupt_signal() {
+		signals::finish_interrupt();
+
+		let interrupted = Interrupted { signal };
+		eprintln!("Error: {interrupted}");
+		std::process::exit(interrupted.exit_code());
+	}
+
+	if let Err(report) = result {
+		// task failures carry their own exit status, which wrapping scripts can branch on
+		if let Some(task_failure) = report.downcast_ref::<TaskFailure>() {
+			eprintln!("Error: {task_failure}");
+			std::process::exit(task_failure.exit_code());
+		}
+
+		if let Some(fan_out_failure) = report.downcast_ref::<FanOutFailure>() {
+			eprintln!("Error: {fan_out_failure}");
+			std::process::exit(fan_out_failure.exit_code());
+		}
+
+		return Err(report);
+	}
+
+	Ok(())
+}
+
+fn run() -> Result<()> {
 	let arg_tokens = args::tokenize_args(std::env::args())?;
 
-	let initial_scope = cli::get_initial_scope_from_args(arg_tokens.clone())?;
+	if !arg_tokens
+		.iter()
+		.take_while(|arg| **arg != ArgToken::Terminator)
+		.any(|arg| matches!(arg, ArgToken::Constant(_) | ArgToken::Param(_, _)))
+	{
+		return Err(eyre!("unexpected end of command, expected command name"));
+	}
+
+	let project_config_path = discover::find_project_config(
+		cli::get_config_flag(&cli::get_initial_scope_from_args(arg_tokens.clone())?)?.as_deref(),
+	)?;
+
+	let loaded_config = parse::load_config(&project_config_path)?;
+	let project_config = &loaded_config.config;
+
+	let arg_tokens = cli::split_leading_bool_flag(arg_tokens, project_config);
 
-	let mut args_iter = arg_tokens.iter().peekable();
+	let mut initial_scope = cli::get_initial_scope_from_args(arg_tokens.clone())?;
 
-	if !matches!(args_iter.peek(), Some(ArgToken::Constant(_))) {
+	// flags can appear anywhere around the command names, they are all collected as options
+	let mut args_iter = arg_tokens
+		.iter()
+		.take_while(|arg| **arg != ArgToken::Terminator)
+		.filter(|arg| matches!(arg, ArgToken::Constant(_)))
+		.peekable();
+
+	if args_iter.peek().is_none() {
 		return Err(eyre!("unexpected end of command, expected command name"));
 	}
 
@@ -27,28 +84,71 @@ fn main() -> Result<()> {
 
 	match command_name.as_str() {
 		"list" => {
-			let project_config = parse::parse_project_config("./example/project.yaml")?;
-
-			commands::list_commands::run(&project_config)?;
+			commands::list_commands::run(&loaded_config)?;
 
 			Ok::<_, Report>(())
 		}
+		"workspaces" => {
+			commands::list_workspaces::run(project_config)?;
+
+			Ok(())
+		}
 		"run" => {
-			let project_config = parse::parse_project_config("./example/project.yaml")?;
+			let command = match args_iter.next() {
+				Some(ArgToken::Constant(name)) => Ok(name),
+				_ => Err(eyre!("no or invalid command name provided")),
+			}?;
+
+			let command = scope::infer_command(command, project_config)?;
+
+			let command_args = cli::get_command_args(&arg_tokens, 2);
+
+			commands::run_command::run(
+				&command,
+				initial_scope,
+				&command_args,
+				project_config,
+				None,
+				discover::get_state_dir(&project_config_path),
+			)?;
+
+			Ok(())
+		}
+		"each" => {
+			let (selection, arg_tokens) = cli::take_each_options(&arg_tokens)?;
+			initial_scope.options = cli::get_options_from_args(&arg_tokens);
 
 			let command = match args_iter.next() {
 				Some(ArgToken::Constant(name)) => Ok(name),
 				_ => Err(eyre!("no or invalid command name provided")),
 			}?;
 
-			commands::run_command::run(command, initial_scope, &project_config)?;
+			let command_args = cli::get_command_args(&arg_tokens, 2);
+
+			commands::run_command::run(
+				command,
+				initial_scope,
+				&command_args,
+				project_config,
+				Some(selection),
+				discover::get_state_dir(&project_config_path),
+			)?;
 
 			Ok(())
 		}
 		_ => {
-			let project_config = parse::parse_project_config("./example/project.yaml")?;
+			let command = scope::infer_command(command_name, project_config)?;
+
+			let command_args = cli::get_command_args(&arg_tokens, 1);
 
-			commands::run_command::run(command_name, initial_scope, &project_config)?;
+			commands::run_command::run(
+				&command,
+				initial_scope,
+				&command_args,
+				project_config,
+				None,
+				discover::get_state_dir(&project_config_path),
+			)?;
 
 			Ok(())
 		}
@@ -2,9 +2,41 @@ use std::{collections::HashMap};
 
 use serde_yaml::{Mapping, Value};
 
-#[derive(Default, Debug)]
+use crate::{config::constants, models::{options::OptionDeclaration, workspace::WorkspaceSelection}};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkingDir {
+	// a directory, or a 'ws:<name>' reference to a workspace
+	Path(String),
+	// an 'in' that's only known once its variables are. a relative result resolves against `base`,
+	// the directory of the scope it's set in, which can be templated itself
+	Templated { base: Box<WorkingDir>, template: String }
+}
+
+impl WorkingDir {
+	// templated dirs are kept as '{ base, template }' mappings in the config
+	pub fn from_value(value: &Value) -> Self {
+		match value {
+			Value::Mapping(templated_dir) => WorkingDir::Templated {
+				base: Box::new(WorkingDir::from_value(templated_dir.get("base").unwrap())),
+				template: templated_dir.get("template").unwrap().as_str().unwrap().to_string()
+			},
+			_ => WorkingDir::Path(value.as_str().unwrap().to_string())
+		}
+	}
+
+	// templated dirs have no path until they've been interpolated
+	pub fn path(&self) -> Option<&str> {
+		match self {
+			WorkingDir::Path(path) => Some(path),
+			WorkingDir::Templated { .. } => None
+		}
+	}
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct TaskCollection {
-	pub working_dir: Option<String>,
+	pub working_dir: Option<WorkingDir>,
 	pub tasks: Vec<String>,
 	pub background: bool,
 	pub parallel: bool
@@ -12,13 +44,22 @@ pub struct TaskCollection {
 
 pub type TaskGrouping = Vec<TaskCollection>;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct CommandScope {
 	pub variables: HashMap<String, Value>,
-	pub working_dir: Option<String>,
+	pub options: HashMap<String, Value>,
+	pub declared_options: Vec<OptionDeclaration>,
+	pub working_dir: Option<WorkingDir>,
 	pub pre_tasks: Option<TaskGrouping>,
 	pub command: TaskCollection,
-	pub post_tasks: Option<TaskGrouping>
+	pub post_tasks: Option<TaskGrouping>,
+	pub forwarded_args: Vec<String>,
+	pub foreach: Option<WorkspaceSelection>,
+	// commands that have to run before this one, as full command names
+	pub needs: Vec<String>,
+	// the command is skipped while its sources and tasks are unchanged and its outputs exist
+	pub sources: Vec<String>,
+	pub outputs: Vec<String>
 }
 
 impl CommandScope {
@@ -34,12 +75,57 @@ impl CommandScope {
 						);
 					}
 				}
+				"options" => {
+					for (k, v) in v.as_mapping().unwrap() {
+						let declaration = OptionDeclaration::from_mapping(k.as_str().unwrap(), v.as_mapping().unwrap());
+
+						if let Some(existing) = self.declared_options.iter_mut().find(|o| o.name == declaration.name) {
+							*existing = declaration;
+						} else {
+							self.declared_options.push(declaration);
+						}
+					}
+				}
 				"-in" => {
 					self.working_dir = None;
 				}
 				"in" => {
-					self.working_dir = Some(v.as_str().unwrap().to_string());
+					self.working_dir = Some(WorkingDir::from_value(v));
 				},
+				"-foreach" => {
+					self.foreach = None;
+				}
+				// only a normalized mapping, a '--foreach' flag has no meaning
+				"foreach" if v.is_mapping() => {
+					self.foreach = Some(WorkspaceSelection::from_mapping(v.as_mapping().unwrap()));
+				}
+				"-needs" => {
+					self.needs.clear();
+				}
+				"needs" if v.is_sequence() => {
+					for command in v.as_sequence().unwrap() {
+						let command = command.as_str().unwrap().to_string();
+
+						if !self.needs.contains(&command) {
+							self.needs.push(command);
+						}
+					}
+				}
+				"-sources" => {
+					self.sources.clear();
+				}
+				"-outputs" => {
+					self.outputs.clear();
+				}
+				"sources" | "outputs" if v.is_sequence() => {
+					let paths = match k.as_str().unwrap() {
+						"sources" => &mut self.sources,
+						"outputs" => &mut self.outputs,
+						_ => unreachable!()
+					};
+
+					*paths = v.as_sequence().unwrap().iter().map(|i| i.as_str().unwrap().to_string()).collect();
+				}
 				"-pre" => {
 					self.pre_tasks = None;
 				}
@@ -60,7 +146,7 @@ impl CommandScope {
 					if let Some(tasks) = tasks_val {
 						let task_mapping = v.as_mapping().unwrap();
 						let task_collection = TaskCollection {
-							working_dir: task_mapping.get("in").map(|in_val| in_val.as_str().unwrap().to_string()),
+							working_dir: task_mapping.get("in").map(WorkingDir::from_value),
 							tasks: task_mapping
 								.get("tasks")
 								.unwrap()
@@ -93,7 +179,7 @@ impl CommandScope {
 					if task_mapping.contains_key("-in") {
 						self.command.working_dir = None;
 					} else if task_mapping.contains_key("in") {
-						self.command.working_dir = Some(task_mapping.get("in").unwrap().as_str().unwrap().to_string());
+						self.command.working_dir = Some(WorkingDir::from_value(task_mapping.get("in").unwrap()));
 					}
 
 					self.command.tasks = task_mapping
@@ -119,9 +205,50 @@ impl CommandScope {
 					} else if task_mapping.contains_key("parallel") {
 						self.command.parallel = task_mapping.get("parallel").unwrap().as_bool().unwrap()
 					}
+
+					// the scope keys of an entry apply to its command alone, as they would on a scope with 'run'
+					let entry_scope = task_mapping
+						.iter()
+						.filter(|(k, _)| constants::COMMAND_ENTRY_SCOPE_KEYS.contains(&k.as_str().unwrap()))
+						.map(|(k, v)| (k.clone(), v.clone()))
+						.collect::<Mapping>();
+
+					self.accumulate_from_mapping(&entry_scope);
 				},
 				_ => {},
 			};
 		}
 	}
+
+	pub fn task_collections(&self) -> Vec<&TaskCollection> {
+		let mut task_collections = Vec::new();
+
+		if let Some(pre_tasks) = self.pre_tasks.as_ref() {
+			task_collections.extend(pre_tasks.iter());
+		}
+
+		task_collections.push(&self.command);
+
+		if let Some(post_tasks) = self.post_tasks.as_ref() {
+			task_collections.extend(post_tasks.iter());
+		}
+
+		task_collections
+	}
+
+	pub fn task_collections_mut(&mut self) -> Vec<&mut TaskCollection> {
+		let mut task_collections = Vec::new();
+
+		if let Some(pre_tasks) = self.pre_tasks.as_mut() {
+			task_collections.extend(pre_tasks.iter_mut());
+		}
+
+		task_collections.push(&mut self.command);
+
+		if let Some(post_tasks) = self.post_tasks.as_mut() {
+			task_collections.extend(post_tasks.iter_mut());
+		}
+
+		task_collections
+	}
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::fmt;
+
+use serde_yaml::{Mapping, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+	String,
+	Number,
+	Bool,
+}
+
+impl OptionType {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"string" => Some(OptionType::String),
+			"number" => Some(OptionType::Number),
+			"bool" => Some(OptionType::Bool),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for OptionType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			OptionType::String => write!(f, "string"),
+			OptionType::Number => write!(f, "number"),
+			OptionType::Bool => write!(f, "bool"),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct OptionDeclaration {
+	pub name: String,
+	pub option_type: OptionType,
+	pub default: Option<Value>,
+	pub required: bool,
+	pub choices: Vec<String>,
+	pub description: Option<String>,
+	pub positional: bool,
+}
+
+impl OptionDeclaration {
+	// expects a declaration mapping that has already been validated by `expand::expand_options`
+	pub fn from_mapping(name: &str, mapping: &Mapping) -> Self {
+		Self {
+			name: name.to_string(),
+			option_type: OptionType::from_name(mapping["type"].as_str().unwrap()).unwrap(),
+			default: mapping.get("default").cloned(),
+			required: mapping["required"].as_bool().unwrap(),
+			choices: mapping["choices"]
+				.as_sequence()
+				.unwrap()
+				.iter()
+				.map(|choice| choice.as_str().unwrap().to_string())
+				.collect(),
+			description: mapping
+				.get("description")
+				.map(|description| description.as_str().unwrap().to_string()),
+			positional: mapping["positional"].as_bool().unwrap(),
+		}
+	}
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use camino::Utf8Path;
+use color_eyre::{Result, eyre::eyre};
+use glob::Pattern;
+use serde_yaml::{Mapping, Value};
+
+use crate::config::constants;
+
+#[derive(Debug, Clone)]
+pub struct Workspace {
+	pub name: String,
+	pub path: String,
+	pub description: Option<String>,
+	pub tags: Vec<String>,
+	pub variables: HashMap<String, Value>,
+	// names of the workspaces that have to run before this one when fanning out
+	pub depends_on: Vec<String>,
+}
+
+impl Workspace {
+	// expects a workspace mapping that has already been normalized by `expand::expand_workspaces`
+	pub fn from_mapping(name: &str, mapping: &Mapping) -> Self {
+		let get_strings = |key: &str| -> Vec<String> {
+			mapping
+				.get(key)
+				.map(|items| {
+					items
+						.as_sequence()
+						.unwrap()
+						.iter()
+						.map(|item| item.as_str().unwrap().to_string())
+						.collect()
+				})
+				.unwrap_or_default()
+		};
+
+		Self {
+			name: name.to_string(),
+			path: mapping["path"].as_str().unwrap().to_string(),
+			description: mapping
+				.get("description")
+				.map(|description| description.as_str().unwrap().to_string()),
+			tags: get_strings("tags"),
+			variables: mapping
+				.get("variables")
+				.map(|variables| {
+					variables
+						.as_mapping()
+						.unwrap()
+						.iter()
+						.map(|(k, v)| {
+							(
+								k.as_str().unwrap().to_string(),
+								v.as_mapping().unwrap().get("value").unwrap().clone(),
+							)
+						})
+						.collect()
+				})
+				.unwrap_or_default(),
+			depends_on: get_strings("depends_on"),
+		}
+	}
+}
+
+// which workspaces a command fans out to, and how many of them it runs in at once
+#[derive(Debug, Clone)]
+pub struct WorkspaceSelection {
+	pub filter: Option<Pattern>,
+	pub tags: Vec<String>,
+	pub jobs: usize,
+}
+
+impl Default for WorkspaceSelection {
+	fn default() -> Self {
+		Self {
+			filter: None,
+			tags: Vec::new(),
+			jobs: 1,
+		}
+	}
+}
+
+impl WorkspaceSelection {
+	// expects a selection mapping that has already been normalized by `expand::expand_foreach`
+	pub fn from_mapping(mapping: &Mapping) -> Self {
+		Self {
+			filter: mapping
+				.get("filter")
+				.map(|filter| Pattern::new(filter.as_str().unwrap()).unwrap()),
+			tags: mapping
+				.get("tags")
+				.map(|tags| {
+					tags.as_sequence()
+						.unwrap()
+						.iter()
+						.map(|tag| tag.as_str().unwrap().to_string())
+						.collect()
+				})
+				.unwrap_or_default(),
+			jobs: mapping
+				.get("jobs")
+				.and_then(Value::as_u64)
+				.map_or(1, |jobs| jobs as usize),
+		}
+	}
+
+	// a workspace is selected when its name matches the filter and it has any of the tags
+	pub fn matches(&self, workspace: &Workspace) -> bool {
+		self.filter
+			.as_ref()
+			.is_none_or(|filter| filter.matches(&workspace.name))
+			&& (self.tags.is_empty() || self.tags.iter().any(|tag| workspace.tags.contains(tag)))
+	}
+}
+
+pub fn get_workspaces(config: &Mapping) -> Vec<Workspace> {
+	let Some(Value::Mapping(workspaces)) = config.get("workspaces") else {
+		return Vec::new();
+	};
+
+	workspaces
+		.iter()
+		.filter_map(|(name, workspace)| {
+			let name = name.as_str()?;
+
+			if constants::WORKSPACES_RESERVED_KEYS.contains(&name) {
+				return None;
+			}
+
+			Some(Workspace::from_mapping(name, workspace.as_mapping()?))
+		})
+		.collect()
+}
+
+// every workspace the directory is inside of, innermost (longest path) first
+pub fn get_containing_workspaces<'a>(
+	workspaces: &'a [Workspace],
+	dir: &Utf8Path,
+) -> Vec<&'a Workspace> {
+	let mut containing_workspaces = workspaces
+		.iter()
+		.filter(|workspace| dir.starts_with(&workspace.path))
+		.collect::<Vec<_>>();
+
+	containing_workspaces.sort_by_key(|workspace| {
+		std::cmp::Reverse(Utf8Path::new(&workspace.path).components().count())
+	});
+
+	containing_workspaces
+}
+
+// orders the workspaces so every workspace comes after the ones it depends on, keeping the order
+// they were defined in otherwise. unknown dependencies and cycles are errors
+pub fn get_dependency_order(workspaces: &[Workspace]) -> Result<Vec<&Workspace>> {
+	let mut ordered_workspaces = Vec::new();
+	let mut dependency_path = Vec::new();
+
+	for workspace in workspaces {
+		visit_dependencies(
+			workspace,
+			workspaces,
+			&mut dependency_path,
+			&mut ordered_workspaces,
+		)?;
+	}
+
+	Ok(ordered_workspaces)
+}
+
+fn visit_dependencies<'a>(
+	workspace: &'a Workspace,
+	workspaces: &'a [Workspace],
+	dependency_path: &mut Vec<&'a str>,
+	ordered_workspaces: &mut Vec<&'a Workspace>,
+) -> Result<()> {
+	if let Some(cycle_start) = dependency_path
+		.iter()
+		.position(|name| *name == workspace.name)
+	{
+		let mut cycle = dependency_path[cycle_start..].to_vec();
+		cycle.push(&workspace.name);
+
+		return Err(eyre!(
+			"workspace dependency cycle detected: {}",
+			cycle.join(" -> ")
+		));
+	}
+
+	if ordered_workspaces
+		.iter()
+		.any(|ordered| ordered.name == workspace.name)
+	{
+		return Ok(());
+	}
+
+	dependency_path.push(&workspace.name);
+
+	for dependency_name in &workspace.depends_on {
+		let Some(dependency) = workspaces
+			.iter()
+			.find(|workspace| workspace.name == *dependency_name)
+		else {
+			return Err(eyre!(
+				"unknown workspace '{dependency_name}' in 'workspaces.{}.depends_on'",
+				workspace.name
+			));
+		};
+
+		visit_dependencies(dependency, workspaces, dependency_path, ordered_workspaces)?;
+	}
+
+	dependency_path.pop();
+	ordered_workspaces.push(workspace);
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn workspace(name: &str, depends_on: &[&str]) -> Workspace {
+		Workspace {
+			name: name.to_string(),
+			path: format!("/{name}"),
+			description: None,
+			tags: Vec::new(),
+			variables: HashMap::new(),
+			depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+		}
+	}
+
+	fn order(workspaces: &[Workspace]) -> Result<Vec<&str>> {
+		Ok(get_dependency_order(workspaces)?
+			.into_iter()
+			.map(|workspace| workspace.name.as_str())
+			.collect())
+	}
+
+	#[test]
+	fn orders_dependencies_first_and_keeps_definition_order_otherwise() {
+		let workspaces = [
+			workspace("web", &["api"]),
+			workspace("docs", &[]),
+			workspace("api", &["core"]),
+			workspace("core", &[]),
+		];
+
+		assert_eq!(order(&workspaces).unwrap(), ["core", "api", "web", "docs"]);
+	}
+
+	#[test]
+	fn reports_dependency_cycles() {
+		let workspaces = [
+			workspace("a", &["b"]),
+			workspace("b", &["c"]),
+			workspace("c", &["a"]),
+		];
+
+		assert_eq!(
+			order(&workspaces).unwrap_err().to_string(),
+			"workspace dependency cycle detected: a -> b -> c -> a"
+		);
+	}
+
+	#[test]
+	fn reports_workspaces_depending_on_themselves() {
+		let workspaces = [workspace("a", &["a"])];
+
+		assert_eq!(
+			order(&workspaces).unwrap_err().to_string(),
+			"workspace dependency cycle detected: a -> a"
+		);
+	}
+
+	#[test]
+	fn reports_unknown_dependencies() {
+		let workspaces = [workspace("web", &["missing"])];
+
+		assert_eq!(
+			order(&workspaces).unwrap_err().to_string(),
+			"unknown workspace 'missing' in 'workspaces.web.depends_on'"
+		);
+	}
+}
@@ -1,34 +1,212 @@
-use color_eyre::eyre::Result;
-use serde_yaml::Mapping;
+use std::collections::HashMap;
 
-use crate::{models, program::args::{self, ArgToken}};
+use color_eyre::eyre::{Result, eyre};
+use serde_yaml::{Mapping, Value};
 
+use crate::{
+	config::{constants, expand},
+	models::{self, options::OptionType, workspace::WorkspaceSelection},
+	program::{args::ArgToken, commands::run_command, scope},
+};
+
+// flags only ever fill in options, the scope of a command comes from the config alone
 pub fn get_initial_scope_from_args(args: Vec<ArgToken>) -> Result<models::command::CommandScope> {
-	let mut args_iter = args.iter().peekable();
-
-	let mut args_mapping = Mapping::new();
-	while let Some(arg) = args_iter.peek()
-		&& matches!(arg, args::ArgToken::Flag(_) | args::ArgToken::Param(_, _))
-	{
-		match args_iter.next() {
-			Some(args::ArgToken::Flag(flag)) => {
-				args_mapping.insert(
-					serde_yaml::Value::String(flag.clone()),
-					serde_yaml::Value::Bool(true),
-				);
+	Ok(models::command::CommandScope {
+		options: get_options_from_args(&args),
+		forwarded_args: get_forwarded_args(&args),
+		..Default::default()
+	})
+}
+
+// a bool flag given before the command name captures the name as its value, as the tokenizer can't
+// know the flag takes none. the name is given back when it names a command that declares the flag
+// as a bool, or any command when no other command name was given, which then reports the flags it
+// doesn't declare
+pub fn split_leading_bool_flag(mut args: Vec<ArgToken>, config: &Mapping) -> Vec<ArgToken> {
+	let leading_flag_count = args
+		.iter()
+		.take_while(|arg| matches!(arg, ArgToken::Flag(_) | ArgToken::Param(_, _)))
+		.count();
+
+	let has_command_name = args
+		.iter()
+		.take_while(|arg| **arg != ArgToken::Terminator)
+		.any(|arg| matches!(arg, ArgToken::Constant(_)));
+
+	for index in 0..leading_flag_count {
+		let ArgToken::Param(flag, value) = &args[index] else {
+			continue;
+		};
+
+		// global options other than '--help' do take a value
+		if flag != "help" && constants::GLOBAL_OPTIONS.contains(&flag.as_str()) {
+			continue;
+		}
+
+		// flags given before 'run' and 'each' belong to the command named after them
+		let command = match value.as_str() {
+			"run" | "each" => args[index + 1..].iter().find_map(|arg| match arg {
+				ArgToken::Constant(name) => Some(name.as_str()),
+				_ => None,
+			}),
+			_ => Some(value.as_str()),
+		};
+
+		let Some(command_scope) = command
+			.and_then(|command| scope::infer_command(command, config).ok())
+			.and_then(|command| {
+				run_command::resolve_command_scope(
+					&command,
+					models::command::CommandScope::default(),
+					config,
+				)
+				.ok()
+			})
+		else {
+			continue;
+		};
+
+		let is_bool_flag = flag == "help"
+			|| !has_command_name
+			|| command_scope.declared_options.iter().any(|declaration| {
+				!declaration.positional
+					&& declaration.name == *flag
+					&& declaration.option_type == OptionType::Bool
+			});
+
+		if is_bool_flag {
+			let (flag, value) = (flag.clone(), value.clone());
+
+			args[index] = ArgToken::Flag(flag);
+			args.insert(index + 1, ArgToken::Constant(value));
+
+			break;
+		}
+	}
+
+	args
+}
+
+pub fn get_config_flag(command_scope: &models::command::CommandScope) -> Result<Option<String>> {
+	match command_scope.options.get("config") {
+		Some(Value::String(path)) => Ok(Some(path.clone())),
+		Some(_) => Err(eyre!("option '--config' requires a path to a config file")),
+		None => Ok(None),
+	}
+}
+
+// '--jobs' (or '-j') limits how many needed commands run at once, one at a time by default
+pub fn get_jobs_flag(command_scope: &models::command::CommandScope) -> Result<usize> {
+	let Some(jobs) = command_scope
+		.options
+		.get("jobs")
+		.or(command_scope.options.get("j"))
+	else {
+		return Ok(1);
+	};
+
+	jobs.as_str()
+		.and_then(|jobs| jobs.parse::<usize>().ok())
+		.filter(|jobs| *jobs > 0)
+		.ok_or_else(|| eyre!("option '--jobs' requires a positive number of jobs"))
+}
+
+// every flag given before the terminator, wherever it appears relative to the command name
+pub fn get_options_from_args(args: &[ArgToken]) -> HashMap<String, Value> {
+	args.iter()
+		.take_while(|arg| **arg != ArgToken::Terminator)
+		.filter_map(|arg| match arg {
+			ArgToken::Flag(flag) => Some((flag.clone(), Value::Bool(true))),
+			ArgToken::Param(flag, value) => Some((flag.clone(), Value::String(value.clone()))),
+			_ => None,
+		})
+		.collect()
+}
+
+// the args that belong to the command itself, which are all args before the terminator other than
+// the leading command names
+pub fn get_command_args(args: &[ArgToken], command_name_count: usize) -> Vec<ArgToken> {
+	let mut skipped_names = 0;
+
+	args.iter()
+		.take_while(|arg| **arg != ArgToken::Terminator)
+		.filter(|arg| {
+			if matches!(arg, ArgToken::Constant(_)) && skipped_names < command_name_count {
+				skipped_names += 1;
+				return false;
 			}
-			Some(args::ArgToken::Param(flag, value)) => {
-				args_mapping.insert(
-					serde_yaml::Value::String(flag.clone()),
-					serde_yaml::Value::String(value.clone()),
-				);
+
+			true
+		})
+		.cloned()
+		.collect()
+}
+
+pub fn get_forwarded_args(args: &[ArgToken]) -> Vec<String> {
+	args.iter()
+		.skip_while(|arg| **arg != ArgToken::Terminator)
+		.filter_map(|arg| match arg {
+			ArgToken::Constant(value) => Some(value.clone()),
+			_ => None,
+		})
+		.collect()
+}
+
+// takes the flags given to 'each' before the command name out of the args, so the command never
+// sees them. '--tag' can list several tags separated by commas
+pub fn take_each_options(args: &[ArgToken]) -> Result<(WorkspaceSelection, Vec<ArgToken>)> {
+	let mut selection = Mapping::new();
+	let mut remaining_args = Vec::new();
+	let mut constant_count = 0;
+
+	for arg in args {
+		if constant_count == 1
+			&& let ArgToken::Flag(flag) | ArgToken::Param(flag, _) = arg
+			&& constants::EACH_OPTIONS.contains(&flag.as_str())
+		{
+			let ArgToken::Param(_, value) = arg else {
+				return Err(eyre!("option '--{flag}' of 'each' requires a value"));
+			};
+
+			match flag.as_str() {
+				"filter" => {
+					selection.insert("filter".into(), value.as_str().into());
+				}
+				"tag" => {
+					selection.insert(
+						"tags".into(),
+						value
+							.split(',')
+							.map(|tag| Value::String(tag.trim().to_string()))
+							.collect::<Vec<_>>()
+							.into(),
+					);
+				}
+				_ => {
+					selection.insert(
+						"jobs".into(),
+						value
+							.parse::<u64>()
+							.map_or_else(|_| value.as_str().into(), Value::from),
+					);
+				}
 			}
-			_ => unreachable!(),
+
+			continue;
+		}
+
+		if matches!(arg, ArgToken::Constant(_)) {
+			constant_count += 1;
 		}
+
+		remaining_args.push(arg.clone());
 	}
 
-	let mut initial_scope = models::command::CommandScope::default();
-	initial_scope.accumulate_from_mapping(&args_mapping);
+	let mut selection = Value::Mapping(selection);
+	expand::expand_foreach("each", &mut selection)?;
 
-	Ok(initial_scope)
+	Ok((
+		WorkspaceSelection::from_mapping(selection.as_mapping().unwrap()),
+		remaining_args,
+	))
 }
@@ -1,57 +1,37 @@
 use color_eyre::eyre::Result;
-use serde_yaml::{Mapping, Value};
 
-use crate::config::constants;
+use crate::config::{
+	names,
+	parse::{ConfigLayer, LoadedConfig},
+};
 
-pub fn run(config: &Mapping) -> Result<()> {
-	let mut commands = Vec::new();
+pub fn run(loaded_config: &LoadedConfig) -> Result<()> {
+	let mut commands = names::get_command_names(&loaded_config.config);
 
-	let mut scopes_to_process: Vec<(String, &Mapping)> = Vec::new();
+	commands.sort_unstable();
 
-	if let Some(Value::Mapping(root_scope)) = config.get("commands") {
-		scopes_to_process.push(("root".to_string(), root_scope));
-	}
+	let layer_commands = loaded_config
+		.layers
+		.iter()
+		.map(|layer| (layer, names::get_command_names(&layer.config)))
+		.collect::<Vec<(&ConfigLayer, Vec<String>)>>();
 
-	while let Some(scope) = scopes_to_process.pop() {
-		if let Some(Value::Mapping(commands_mapping)) = scope.1.get("commands") {
-			for key in commands_mapping.keys() {
-				let key = key.as_str().unwrap();
-				commands.push(if scope.0 == "root" {
-					key.to_string()
-				} else if key == "." {
-					scope.0.clone()
-				} else {
-					format!("{}{}{}", scope.0, constants::SCOPE_SEPARATOR, key)
-				});
-			}
-		}
-
-		for key in scope.1.keys() {
-			let key = key.as_str().unwrap();
-
-			if constants::SCOPE_RESERVED_KEYS.contains(&key) {
-				continue;
-			}
-
-			let value = scope.1.get(key).unwrap();
-			if value.is_mapping() {
-				scopes_to_process.push((
-					if scope.0 == "root" {
-						key.to_string()
-					} else {
-						format!("{}{}{}", scope.0, constants::SCOPE_SEPARATOR, key)
-					},
-					value.as_mapping().unwrap(),
-				));
-			}
-		}
-	}
-
-	commands.sort_unstable();
+	let width = commands
+		.iter()
+		.map(|command| command.len())
+		.max()
+		.unwrap_or(0);
 
 	println!("\nAvailable commands:");
 	for command in commands {
-		println!("- {}", command);
+		// layers are in order of precedence, so the first one defining the command is where it came from
+		let source = layer_commands
+			.iter()
+			.find(|(_, layer_commands)| layer_commands.contains(&command))
+			.map(|(layer, _)| layer.path.as_str())
+			.unwrap_or_default();
+
+		println!("- {command:<width$}  ({source})");
 	}
 
 	Ok(())
@@ -0,0 +1,42 @@
+use color_eyre::eyre::Result;
+use serde_yaml::Mapping;
+
+use crate::models::workspace;
+
+pub fn run(config: &Mapping) -> Result<()> {
+	let mut workspaces = workspace::get_workspaces(config);
+
+	if workspaces.is_empty() {
+		println!("\nno workspaces are defined");
+		return Ok(());
+	}
+
+	workspaces.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+	let name_width = workspaces
+		.iter()
+		.map(|workspace| workspace.name.len())
+		.max()
+		.unwrap_or(0);
+
+	println!("\nWorkspaces:");
+	for workspace in workspaces {
+		let mut details = vec![workspace.path.clone()];
+
+		if !workspace.tags.is_empty() {
+			details.push(format!("[{}]", workspace.tags.join(", ")));
+		}
+
+		if !workspace.depends_on.is_empty() {
+			details.push(format!("(depends on {})", workspace.depends_on.join(", ")));
+		}
+
+		if let Some(description) = &workspace.description {
+			details.push(format!("- {description}"));
+		}
+
+		println!("- {:<name_width$}  {}", workspace.name, details.join(" "));
+	}
+
+	Ok(())
+}
@@ -1,2 +1,3 @@
 pub mod list_commands;
+pub mod list_workspaces;
 pub mod run_command;
@@ -1,10 +1,51 @@
+use camino::Utf8PathBuf;
 use color_eyre::eyre::{Result, eyre};
 use serde_yaml::Mapping;
 
-use crate::{config::constants::{self, SCOPE_RESERVED_KEYS}, models};
+use crate::{config::{constants::SCOPE_RESERVED_KEYS, settings}, exec::{self, fingerprint::Fingerprint}, log_info, models::{self, workspace::WorkspaceSelection}, program::{args::ArgToken, cli, fanout, invocation::Invocation, needs, options}};
 
-pub fn run(command: &str, mut command_scope: models::command::CommandScope, config: &Mapping) -> Result<()> {
-	let mut command_parts = command.split(constants::SCOPE_SEPARATOR).peekable();
+pub fn run(command: &str, command_scope: models::command::CommandScope, command_args: &[ArgToken], config: &Mapping, selection: Option<WorkspaceSelection>, state_dir: Utf8PathBuf) -> Result<()> {
+	let invocation = Invocation::new(cli::get_jobs_flag(&command_scope)?, state_dir);
+
+	run_in_invocation(command, command_scope, command_args, config, selection, &invocation, None)
+}
+
+fn run_in_invocation(command: &str, command_scope: models::command::CommandScope, command_args: &[ArgToken], config: &Mapping, selection: Option<WorkspaceSelection>, invocation: &Invocation, output_prefix: Option<&str>) -> Result<()> {
+	let mut command_scope = resolve_command_scope(command, command_scope, config)?;
+
+	options::apply_declared_options(command, &mut command_scope, command_args)?;
+
+	if options::is_help_requested(&command_scope) {
+		options::print_usage(command, &command_scope);
+		return Ok(());
+	}
+
+	needs::run_needed_commands(command, &command_scope.needs, config, invocation)?;
+
+	run_resolved(command, command_scope, config, selection, invocation, output_prefix)
+}
+
+// runs a command another command needs, once everything it needs itself has already run
+pub fn run_needed(command: &str, config: &Mapping, invocation: &Invocation, output_prefix: Option<&str>) -> Result<()> {
+	let mut command_scope = resolve_command_scope(command, models::command::CommandScope::default(), config)?;
+
+	options::apply_declared_options(command, &mut command_scope, &[])?;
+
+	run_resolved(command, command_scope, config, None, invocation, output_prefix)
+}
+
+fn run_resolved(command: &str, mut command_scope: models::command::CommandScope, config: &Mapping, selection: Option<WorkspaceSelection>, invocation: &Invocation, output_prefix: Option<&str>) -> Result<()> {
+	// a selection given to 'each' takes precedence over the scope's 'foreach'
+	match selection.or(command_scope.foreach.take()) {
+		Some(selection) => fanout::run_for_workspaces(command, &command_scope, config, &selection, invocation),
+		None => execute(command, command_scope, config, invocation, output_prefix),
+	}
+}
+
+// accumulates the scope of a command from every scope on the way to it
+pub fn resolve_command_scope(command: &str, mut command_scope: models::command::CommandScope, config: &Mapping) -> Result<models::command::CommandScope> {
+	let scope_separator = settings::get().scope_separator.as_str();
+	let mut command_parts = command.split(scope_separator).peekable();
 
 	if !config.contains_key("commands") {
 		return Err(eyre!(
@@ -14,12 +55,17 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 
 	let commands = config.get("commands").unwrap().as_mapping().unwrap();
 
+	command_scope.accumulate_from_mapping(commands);
+
 	let (mut prev_path, mut prev_scope) = (String::new(), commands);
 	let mut last_scope_name = "";
+	// set when the last part of the name is a command entry rather than a scope of its own
+	let mut is_command_entry = false;
 	while let Some(curr_scope_name) = command_parts.next() {
 		if !prev_scope.contains_key(curr_scope_name) {
 			if command_parts.peek().is_none() {
 				last_scope_name = curr_scope_name;
+				is_command_entry = true;
 				continue;
 			}
 			
@@ -39,6 +85,15 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 			.as_mapping()
 			.unwrap();
 
+		// a scope without a command of its own doesn't hide a command entry of the same name
+		let has_own_command = curr_scope.get("commands").and_then(|c| c.as_mapping()).is_some_and(|c| c.contains_key("."));
+		let has_command_entry = prev_scope.get("commands").and_then(|c| c.as_mapping()).is_some_and(|c| c.contains_key(curr_scope_name));
+		if command_parts.peek().is_none() && !has_own_command && has_command_entry {
+			last_scope_name = curr_scope_name;
+			is_command_entry = true;
+			continue;
+		}
+
 		command_scope.accumulate_from_mapping(curr_scope);
 
 		last_scope_name = curr_scope_name;
@@ -49,7 +104,7 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 				if prev_path.is_empty() {
 					""
 				} else {
-					constants::SCOPE_SEPARATOR
+					scope_separator
 				},
 				curr_scope_name
 			),
@@ -58,11 +113,24 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 	}
 
 	if let Some(commands_mapping) = prev_scope.get("commands") {
-		let command_name = if commands_mapping.as_mapping().unwrap().contains_key(".") {
+		let command_name = if !is_command_entry && commands_mapping.as_mapping().unwrap().contains_key(".") {
 			"."
 		} else {
 			last_scope_name
 		};
+
+		// a mistyped name would otherwise resolve to an empty scope and run nothing successfully
+		if !commands_mapping.as_mapping().unwrap().contains_key(command_name) {
+			return Err(eyre!(
+				"unable to find command '{command}' in config{}", if !is_command_entry {
+					format!(". '{prev_path}' is a scope without a command of its own")
+				} else if prev_path.is_empty() {
+					String::new()
+				} else {
+					format!(". could not find '{last_scope_name}' in '{prev_path}'")
+				},
+			));
+		}
 		
 		let mut filtered_commands_mapping = commands_mapping.as_mapping().unwrap().clone();
 		let commands_keys = filtered_commands_mapping.keys().cloned().collect::<Vec<_>>();
@@ -74,11 +142,11 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 		}
 		
 		command_scope.accumulate_from_mapping(&filtered_commands_mapping);
-		
-		println!("would execute command scope: {command_scope:#?}");
 	} else {
 		return Err(eyre!(
-			"unable to find command '{last_scope_name}' in config{}", if prev_path.is_empty() {
+			"unable to find command '{last_scope_name}' in config{}", if !is_command_entry {
+				format!(". '{prev_path}' is a scope without a command of its own")
+			} else if prev_path.is_empty() {
 				String::new()
 			} else {
 				format!(". could not find '{last_scope_name}' in '{prev_path}'")
@@ -86,5 +154,40 @@ pub fn run(command: &str, mut command_scope: models::command::CommandScope, conf
 		));
 	}
 
-	Ok(())
+	Ok(command_scope)
 }
+
+pub fn execute(scope_path: &str, mut command_scope: models::command::CommandScope, config: &Mapping, invocation: &Invocation, output_prefix: Option<&str>) -> Result<()> {
+	exec::forward::forward_args(&mut command_scope);
+	// working dirs are resolved on both sides of interpolating, as the workspace variables depend
+	// on where the command runs, while templated dirs can only resolve to a workspace afterwards
+	exec::workspace::resolve_workspace_dirs(scope_path, &mut command_scope, config)?;
+	exec::workspace::inject_workspace_variables(&mut command_scope, config)?;
+	exec::interpolate::interpolate_command_scope(&mut command_scope)?;
+	exec::workspace::resolve_workspace_dirs(scope_path, &mut command_scope, config)?;
+
+	// a referenced command is resolved like any other, with its own scope and no args of its own
+	let run_reference = |command: &str| {
+		invocation.run_once(command, || {
+			run_in_invocation(command, models::command::CommandScope::default(), &[], config, None, invocation, output_prefix)
+		})
+	};
+
+	let context = exec::runner::RunContext { output_prefix, run_reference: &run_reference };
+
+	if command_scope.sources.is_empty() && command_scope.outputs.is_empty() {
+		return exec::runner::run_command_scope(scope_path, &command_scope, &context);
+	}
+
+	// commands that declare sources or outputs only run again once something they depend on changed
+	let fingerprint = Fingerprint::compute(scope_path, &command_scope, &invocation.state_dir)?;
+
+	if fingerprint.is_up_to_date(&command_scope)? {
+		log_info!("{scope_path} - up to date, skipping");
+		return Ok(());
+	}
+
+	exec::runner::run_command_scope(scope_path, &command_scope, &context)?;
+
+	fingerprint.save()
+}
\ No newline at end of file
@@ -0,0 +1,134 @@
+use color_eyre::{Report, Result, eyre::eyre};
+use serde_yaml::Mapping;
+
+use crate::{
+	exec::{
+		error::{FanOutFailure, TaskFailure},
+		output,
+	},
+	log_info,
+	models::{
+		command::{CommandScope, WorkingDir},
+		workspace::{self, WorkspaceSelection},
+	},
+	program::{
+		commands::run_command,
+		invocation::Invocation,
+		schedule::{self, Outcome, ScheduledNode},
+	},
+};
+
+// runs the command once in every selected workspace, with its 'in' set to the workspace path, after
+// the workspaces they depend on. a failure in one workspace doesn't stop unrelated ones, they are
+// all reported in a summary at the end
+pub fn run_for_workspaces(
+	command: &str,
+	command_scope: &CommandScope,
+	config: &Mapping,
+	selection: &WorkspaceSelection,
+	invocation: &Invocation,
+) -> Result<()> {
+	let workspaces = workspace::get_workspaces(config);
+	let selected_workspaces = workspace::get_dependency_order(&workspaces)?
+		.into_iter()
+		.filter(|workspace| selection.matches(workspace))
+		.collect::<Vec<_>>();
+
+	if selected_workspaces.is_empty() {
+		return Err(eyre!(
+			"unable to run '{command}' for each workspace, no workspaces {}",
+			if workspaces.is_empty() {
+				"are defined"
+			} else {
+				"match the selection"
+			}
+		));
+	}
+
+	let jobs = selection.jobs.min(selected_workspaces.len());
+	let name_width = selected_workspaces
+		.iter()
+		.map(|workspace| workspace.name.chars().count())
+		.max()
+		.unwrap_or(0);
+
+	log_info!(
+		"{command} - running in {} workspaces ({jobs} at a time)",
+		selected_workspaces.len()
+	);
+
+	// dependencies outside of the selection are assumed to be taken care of already
+	let nodes = selected_workspaces
+		.iter()
+		.map(|workspace| ScheduledNode {
+			name: workspace.name.clone(),
+			dependencies: selected_workspaces
+				.iter()
+				.enumerate()
+				.filter(|(_, dependency)| workspace.depends_on.contains(&dependency.name))
+				.map(|(dependency_index, _)| dependency_index)
+				.collect(),
+		})
+		.collect::<Vec<_>>();
+
+	// unlike a single command, workspaces running at once can't share the terminal, so each of
+	// them has its output prefixed with the workspace name instead
+	let outcomes = schedule::run_graph(&nodes, jobs, |workspace_index| {
+		let workspace = selected_workspaces[workspace_index];
+
+		let mut workspace_scope = command_scope.clone();
+		workspace_scope.working_dir = Some(WorkingDir::Path(workspace.path.clone()));
+		workspace_scope.command.working_dir = Some(WorkingDir::Path(workspace.path.clone()));
+
+		let output_prefix =
+			(jobs > 1).then(|| output::task_prefix(workspace_index, &workspace.name, name_width));
+
+		run_command::execute(
+			format!("{command} [{}]", workspace.name).as_str(),
+			workspace_scope,
+			config,
+			invocation,
+			output_prefix.as_deref(),
+		)
+	});
+
+	let mut failed_workspaces = Vec::new();
+	let mut exit_code = None;
+
+	eprintln!("\nSummary:");
+	for (workspace, outcome) in selected_workspaces.iter().zip(&outcomes) {
+		let outcome = match outcome {
+			Some(Outcome::Passed) => "passed".to_string(),
+			Some(Outcome::Failed(report)) => {
+				failed_workspaces.push(workspace.name.clone());
+				exit_code.get_or_insert_with(|| get_exit_code(report));
+
+				format!("failed - {report}")
+			}
+			Some(Outcome::Blocked(dependency)) => {
+				format!("skipped - depends on '{dependency}', which didn't pass")
+			}
+			None => "skipped".to_string(),
+		};
+
+		eprintln!("- {:<name_width$}  {outcome}", workspace.name);
+	}
+
+	if !failed_workspaces.is_empty() {
+		return Err(FanOutFailure {
+			command: command.to_string(),
+			failed_workspaces,
+			workspace_count: selected_workspaces.len(),
+			exit_code: exit_code.unwrap(),
+		}
+		.into());
+	}
+
+	Ok(())
+}
+
+fn get_exit_code(report: &Report) -> i32 {
+	report
+		.downcast_ref::<TaskFailure>()
+		.map_or(1, TaskFailure::exit_code)
+}
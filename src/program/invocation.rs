@@ -0,0 +1,59 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+use camino::Utf8PathBuf;
+use color_eyre::{Result, eyre::eyre};
+
+use crate::log_info;
+
+// commands referred to by tasks or needed by other commands run at most once per invocation,
+// however many refer to them. each command has its own lock, so a command running alongside others
+// waits for a command another one already started rather than running it again
+pub struct Invocation {
+	// how many needed commands can run at once
+	pub jobs: usize,
+	// where fingerprints of commands that declare sources or outputs are kept
+	pub state_dir: Utf8PathBuf,
+	referenced_commands: Mutex<HashMap<String, Arc<Mutex<Option<bool>>>>>,
+}
+
+impl Invocation {
+	pub fn new(jobs: usize, state_dir: Utf8PathBuf) -> Self {
+		Self {
+			jobs,
+			state_dir,
+			referenced_commands: Mutex::new(HashMap::new()),
+		}
+	}
+
+	pub fn run_once(&self, command: &str, run: impl FnOnce() -> Result<()>) -> Result<()> {
+		let outcome = self
+			.referenced_commands
+			.lock()
+			.unwrap()
+			.entry(command.to_string())
+			.or_default()
+			.clone();
+
+		let mut outcome = outcome.lock().unwrap();
+
+		match *outcome {
+			Some(true) => {
+				log_info!("{command} - already ran in this invocation, skipping");
+
+				Ok(())
+			}
+			Some(false) => Err(eyre!(
+				"referenced command '{command}' already failed in this invocation"
+			)),
+			None => {
+				let result = run();
+				*outcome = Some(result.is_ok());
+
+				result
+			}
+		}
+	}
+}
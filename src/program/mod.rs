@@ -1,3 +1,9 @@
 pub mod args;
 pub mod cli;
 pub mod commands;
+pub mod fanout;
+pub mod invocation;
+pub mod needs;
+pub mod options;
+pub mod schedule;
+pub mod scope;
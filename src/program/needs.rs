@@ -0,0 +1,106 @@
+use color_eyre::Result;
+use serde_yaml::Mapping;
+
+use crate::{
+	exec::{output, signals},
+	log_info,
+	models::command::CommandScope,
+	program::{
+		commands::run_command,
+		invocation::Invocation,
+		schedule::{self, Outcome, ScheduledNode},
+	},
+};
+
+// runs every command the command needs, and everything those need in turn, each after the commands
+// it needs itself. independent commands run alongside each other up to the invocation's job limit
+pub fn run_needed_commands(
+	command: &str,
+	needs: &[String],
+	config: &Mapping,
+	invocation: &Invocation,
+) -> Result<()> {
+	if needs.is_empty() {
+		return Ok(());
+	}
+
+	let mut needed_commands = Vec::new();
+	for needed_command in needs {
+		collect_needed_commands(needed_command, config, &mut needed_commands)?;
+	}
+
+	let nodes = needed_commands
+		.iter()
+		.map(|(needed_command, needs)| ScheduledNode {
+			name: needed_command.clone(),
+			dependencies: needs
+				.iter()
+				.map(|need| {
+					needed_commands
+						.iter()
+						.position(|(needed_command, _)| needed_command == need)
+						.unwrap()
+				})
+				.collect(),
+		})
+		.collect::<Vec<_>>();
+
+	let jobs = invocation.jobs.min(nodes.len());
+	let name_width = nodes
+		.iter()
+		.map(|node| node.name.chars().count())
+		.max()
+		.unwrap_or(0);
+
+	log_info!(
+		"{command} - running {} needed commands ({jobs} at a time)",
+		nodes.len()
+	);
+
+	let outcomes = schedule::run_graph(&nodes, jobs, |node_index| {
+		let needed_command = nodes[node_index].name.as_str();
+
+		let output_prefix =
+			(jobs > 1).then(|| output::task_prefix(node_index, needed_command, name_width));
+
+		invocation.run_once(needed_command, || {
+			run_command::run_needed(needed_command, config, invocation, output_prefix.as_deref())
+		})
+	});
+
+	signals::check_interrupted()?;
+
+	// the first failure is reported as is, so its exit status carries through
+	for outcome in outcomes {
+		if let Some(Outcome::Failed(report)) = outcome {
+			return Err(report);
+		}
+	}
+
+	Ok(())
+}
+
+// collects the needed commands in an order where every command comes after the ones it needs.
+// cycles have already been ruled out when loading the config
+fn collect_needed_commands(
+	command: &str,
+	config: &Mapping,
+	needed_commands: &mut Vec<(String, Vec<String>)>,
+) -> Result<()> {
+	if needed_commands
+		.iter()
+		.any(|(needed_command, _)| needed_command == command)
+	{
+		return Ok(());
+	}
+
+	let needs = run_command::resolve_command_scope(command, CommandScope::default(), config)?.needs;
+
+	for needed_command in &needs {
+		collect_needed_commands(needed_command, config, needed_commands)?;
+	}
+
+	needed_commands.push((command.to_string(), needs));
+
+	Ok(())
+}
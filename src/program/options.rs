@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::{Result, eyre};
+use serde_yaml::Value;
+
+use crate::{
+	config::constants,
+	models::{
+		command::CommandScope,
+		options::{OptionDeclaration, OptionType},
+	},
+	program::args::ArgToken,
+};
+
+pub fn is_help_requested(command_scope: &CommandScope) -> bool {
+	command_scope.options.contains_key("help")
+}
+
+// rebuilds the command options from the args given for it, validating them against the options the
+// command declares. commands without declarations accept any flag
+pub fn apply_declared_options(
+	command: &str,
+	command_scope: &mut CommandScope,
+	command_args: &[ArgToken],
+) -> Result<()> {
+	if command_scope.declared_options.is_empty() {
+		return Ok(());
+	}
+
+	let declared_options = &command_scope.declared_options;
+	let mut options = HashMap::new();
+	let mut positionals = Vec::new();
+
+	for arg in command_args {
+		let (name, value) = match arg {
+			ArgToken::Flag(name) => (name, None),
+			ArgToken::Param(name, value) => (name, Some(value)),
+			ArgToken::Constant(value) => {
+				positionals.push(value.clone());
+				continue;
+			}
+			ArgToken::Terminator => break,
+		};
+
+		if constants::GLOBAL_OPTIONS.contains(&name.as_str()) {
+			options.insert(
+				name.clone(),
+				value.map_or(Value::Bool(true), |value| Value::String(value.clone())),
+			);
+			continue;
+		}
+
+		let Some(declaration) = declared_options
+			.iter()
+			.find(|declaration| !declaration.positional && declaration.name == *name)
+		else {
+			return Err(unknown_option_error(command, name, declared_options));
+		};
+
+		let value = match (declaration.option_type, value) {
+			(OptionType::Bool, None) => Value::Bool(true),
+			(OptionType::Bool, Some(value)) => match value.as_str() {
+				"true" => Value::Bool(true),
+				"false" => Value::Bool(false),
+				// the tokenizer can't know a flag takes no value, so the next arg was captured by it
+				_ => {
+					positionals.push(value.clone());
+					Value::Bool(true)
+				}
+			},
+			(_, None) => {
+				return Err(eyre!(
+					"option '--{name}' for '{command}' requires a {} value",
+					declaration.option_type
+				));
+			}
+			(_, Some(value)) => parse_value(command, declaration, value)?,
+		};
+
+		options.insert(name.clone(), value);
+	}
+
+	let mut positionals = positionals.into_iter();
+	for declaration in declared_options
+		.iter()
+		.filter(|declaration| declaration.positional)
+	{
+		if let Some(value) = positionals.next() {
+			let value = parse_value(command, declaration, &value)?;
+			options.insert(declaration.name.clone(), value);
+		}
+	}
+
+	let unexpected = positionals.collect::<Vec<_>>();
+	if !unexpected.is_empty() {
+		return Err(eyre!(
+			"unexpected arguments for '{command}': {}\nrun 'project-manager {command} --help' for usage",
+			unexpected.join(" ")
+		));
+	}
+
+	// help is printed instead of running, so missing values shouldn't get in the way of it
+	if options.contains_key("help") {
+		command_scope.options = options;
+		return Ok(());
+	}
+
+	for declaration in declared_options {
+		if let Some(value) = options.get(&declaration.name) {
+			let value_str = match value {
+				Value::String(value) => value.clone(),
+				Value::Number(value) => value.to_string(),
+				_ => continue,
+			};
+
+			if !declaration.choices.is_empty() && !declaration.choices.contains(&value_str) {
+				return Err(eyre!(
+					"invalid value '{value_str}' for {} of '{command}'\nvalue must be one of: {}",
+					display_name(declaration),
+					declaration.choices.join(", ")
+				));
+			}
+		} else if let Some(default) = &declaration.default {
+			options.insert(declaration.name.clone(), default.clone());
+		} else if declaration.required {
+			return Err(eyre!(
+				"missing required {} for '{command}'\nrun 'project-manager {command} --help' for usage",
+				display_name(declaration)
+			));
+		}
+	}
+
+	command_scope.options = options;
+
+	Ok(())
+}
+
+fn parse_value(command: &str, declaration: &OptionDeclaration, value: &str) -> Result<Value> {
+	let name = display_name(declaration);
+
+	match declaration.option_type {
+		OptionType::String => Ok(Value::String(value.to_string())),
+		OptionType::Number => serde_yaml::from_str::<Value>(value)
+			.ok()
+			.filter(Value::is_number)
+			.ok_or_else(|| {
+				eyre!("invalid value '{value}' for {name} of '{command}'\nvalue must be a number")
+			}),
+		OptionType::Bool => match value {
+			"true" => Ok(Value::Bool(true)),
+			"false" => Ok(Value::Bool(false)),
+			_ => Err(eyre!(
+				"invalid value '{value}' for {name} of '{command}'\nvalue must be 'true' or 'false'"
+			)),
+		},
+	}
+}
+
+fn display_name(declaration: &OptionDeclaration) -> String {
+	if declaration.positional {
+		format!("argument '<{}>'", declaration.name)
+	} else {
+		format!("option '--{}'", declaration.name)
+	}
+}
+
+fn unknown_option_error(
+	command: &str,
+	name: &str,
+	declared_options: &[OptionDeclaration],
+) -> color_eyre::Report {
+	let suggestion = declared_options
+		.iter()
+		.filter(|declaration| !declaration.positional)
+		.map(|declaration| (edit_distance(name, &declaration.name), &declaration.name))
+		.filter(|(distance, _)| *distance <= 2)
+		.min_by_key(|(distance, _)| *distance)
+		.map(|(_, suggestion)| format!("\ndid you mean '--{suggestion}'?"))
+		.unwrap_or_default();
+
+	eyre!(
+		"unknown option '--{name}' for '{command}'{suggestion}\nrun 'project-manager {command} --help' for usage"
+	)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut prev_row = (0..=b.len()).collect::<Vec<_>>();
+
+	for (i, a_char) in a.chars().enumerate() {
+		let mut row = vec![i + 1; b.len() + 1];
+
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+			row[j + 1] = (prev_row[j] + substitution_cost)
+				.min(prev_row[j + 1] + 1)
+				.min(row[j] + 1);
+		}
+
+		prev_row = row;
+	}
+
+	prev_row[b.len()]
+}
+
+pub fn print_usage(command: &str, command_scope: &CommandScope) {
+	let declared_options = &command_scope.declared_options;
+	let positionals = declared_options
+		.iter()
+		.filter(|declaration| declaration.positional)
+		.collect::<Vec<_>>();
+	let flags = declared_options
+		.iter()
+		.filter(|declaration| !declaration.positional)
+		.collect::<Vec<_>>();
+
+	let mut usage = format!("project-manager {command}");
+	if !flags.is_empty() {
+		usage.push_str(" [options]");
+	}
+	for positional in &positionals {
+		if positional.required {
+			usage.push_str(&format!(" <{}>", positional.name));
+		} else {
+			usage.push_str(&format!(" [{}]", positional.name));
+		}
+	}
+
+	println!("\nUsage: {usage}");
+
+	if !positionals.is_empty() {
+		println!("\nArguments:");
+		print_declarations(&positionals);
+	}
+
+	if !flags.is_empty() {
+		println!("\nOptions:");
+		print_declarations(&flags);
+	}
+
+	if declared_options.is_empty() {
+		println!("\nthis command does not declare any options");
+	}
+}
+
+fn print_declarations(declarations: &[&OptionDeclaration]) {
+	let labels = declarations
+		.iter()
+		.map(|declaration| {
+			if declaration.positional {
+				format!("<{}>", declaration.name)
+			} else if declaration.option_type == OptionType::Bool {
+				format!("--{}", declaration.name)
+			} else {
+				format!("--{} <{}>", declaration.name, declaration.option_type)
+			}
+		})
+		.collect::<Vec<_>>();
+	let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+
+	for (declaration, label) in declarations.iter().zip(labels) {
+		let mut details = Vec::new();
+
+		if let Some(description) = &declaration.description {
+			details.push(description.clone());
+		}
+		if declaration.required {
+			details.push("[required]".to_string());
+		}
+		if !declaration.choices.is_empty() {
+			details.push(format!("[choices: {}]", declaration.choices.join(", ")));
+		}
+		if let Some(default) = &declaration.default {
+			let default = serde_yaml::to_string(default).unwrap_or_default();
+			details.push(format!("[default: {}]", default.trim()));
+		}
+
+		println!("  {label:<label_width$}  {}", details.join(" "));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn declaration(name: &str, option_type: OptionType) -> OptionDeclaration {
+		OptionDeclaration {
+			name: name.to_string(),
+			option_type,
+			default: None,
+			required: false,
+			choices: Vec::new(),
+			description: None,
+			positional: false,
+		}
+	}
+
+	fn deploy_scope() -> CommandScope {
+		CommandScope {
+			declared_options: vec![
+				OptionDeclaration {
+					required: true,
+					choices: vec!["staging".to_string(), "production".to_string()],
+					..declaration("env", OptionType::String)
+				},
+				declaration("release", OptionType::Bool),
+				OptionDeclaration {
+					default: Some(Value::from(3)),
+					..declaration("retries", OptionType::Number)
+				},
+				OptionDeclaration {
+					positional: true,
+					..declaration("target", OptionType::String)
+				},
+			],
+			..Default::default()
+		}
+	}
+
+	fn apply(args: &[ArgToken]) -> Result<CommandScope> {
+		let mut command_scope = deploy_scope();
+		apply_declared_options("deploy", &mut command_scope, args)?;
+
+		Ok(command_scope)
+	}
+
+	fn param(name: &str, value: &str) -> ArgToken {
+		ArgToken::Param(name.to_string(), value.to_string())
+	}
+
+	#[test]
+	fn applies_values_defaults_and_positionals() {
+		let command_scope = apply(&[
+			param("env", "staging"),
+			ArgToken::Flag("release".to_string()),
+			ArgToken::Constant("web".to_string()),
+		])
+		.unwrap();
+
+		assert_eq!(command_scope.options["env"], Value::from("staging"));
+		assert_eq!(command_scope.options["release"], Value::Bool(true));
+		assert_eq!(command_scope.options["retries"], Value::from(3));
+		assert_eq!(command_scope.options["target"], Value::from("web"));
+	}
+
+	#[test]
+	fn gives_values_captured_by_bool_flags_back_as_positionals() {
+		let command_scope = apply(&[param("env", "staging"), param("release", "web")]).unwrap();
+
+		assert_eq!(command_scope.options["release"], Value::Bool(true));
+		assert_eq!(command_scope.options["target"], Value::from("web"));
+	}
+
+	#[test]
+	fn rejects_missing_required_options() {
+		let err = apply(&[]).unwrap_err();
+
+		assert!(
+			err.to_string()
+				.starts_with("missing required option '--env' for 'deploy'")
+		);
+	}
+
+	#[test]
+	fn rejects_values_outside_of_choices() {
+		let err = apply(&[param("env", "dev")]).unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"invalid value 'dev' for option '--env' of 'deploy'\nvalue must be one of: staging, production"
+		);
+	}
+
+	#[test]
+	fn rejects_values_of_the_wrong_type() {
+		let err = apply(&[param("env", "staging"), param("retries", "many")]).unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"invalid value 'many' for option '--retries' of 'deploy'\nvalue must be a number"
+		);
+	}
+
+	#[test]
+	fn rejects_options_without_their_value() {
+		let err = apply(&[ArgToken::Flag("env".to_string())]).unwrap_err();
+
+		assert_eq!(
+			err.to_string(),
+			"option '--env' for 'deploy' requires a string value"
+		);
+	}
+
+	#[test]
+	fn suggests_close_option_names() {
+		let err = apply(&[param("evn", "staging")]).unwrap_err();
+
+		assert!(err.to_string().contains("did you mean '--env'?"));
+	}
+
+	#[test]
+	fn rejects_unexpected_arguments() {
+		let err = apply(&[
+			param("env", "staging"),
+			ArgToken::Constant("web".to_string()),
+			ArgToken::Constant("api".to_string()),
+		])
+		.unwrap_err();
+
+		assert!(
+			err.to_string()
+				.starts_with("unexpected arguments for 'deploy': api")
+		);
+	}
+
+	#[test]
+	fn accepts_any_flag_without_declarations() {
+		let mut command_scope = CommandScope::default();
+
+		apply_declared_options("free", &mut command_scope, &[param("anything", "1")]).unwrap();
+	}
+}
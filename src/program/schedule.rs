@@ -0,0 +1,183 @@
+use std::sync::{Condvar, Mutex};
+
+use color_eyre::{Report, Result};
+
+use crate::exec::signals;
+
+pub enum Outcome {
+	Passed,
+	Failed(Report),
+	// a node isn't run when a node it depends on didn't pass
+	Blocked(String),
+}
+
+pub struct ScheduledNode {
+	pub name: String,
+	// indices of the nodes that have to pass before this one runs
+	pub dependencies: Vec<usize>,
+}
+
+// tracks which nodes are still waiting to run, so workers can pick up any node whose dependencies
+// have all passed
+struct Schedule {
+	pending: Vec<usize>,
+	outcomes: Vec<Option<Outcome>>,
+}
+
+impl Schedule {
+	// the next node that can run, if any. nodes that can never run are resolved on the way
+	fn take_ready(&mut self, nodes: &[ScheduledNode]) -> Option<usize> {
+		let mut i = 0;
+		while i < self.pending.len() {
+			let node_index = self.pending[i];
+
+			let mut ready = true;
+			let mut blocked_by = None;
+
+			for &dependency_index in &nodes[node_index].dependencies {
+				match &self.outcomes[dependency_index] {
+					Some(Outcome::Passed) => {}
+					Some(_) => blocked_by = Some(nodes[dependency_index].name.clone()),
+					None => ready = false,
+				}
+			}
+
+			if let Some(blocked_by) = blocked_by {
+				self.pending.remove(i);
+				self.outcomes[node_index] = Some(Outcome::Blocked(blocked_by));
+
+				// an earlier node may have been waiting on this one
+				i = 0;
+				continue;
+			}
+
+			if ready {
+				return Some(self.pending.remove(i));
+			}
+
+			i += 1;
+		}
+
+		None
+	}
+}
+
+// runs every node once the nodes it depends on have passed, with up to 'jobs' of them at once and
+// the others picked up in the order they were given. nodes that haven't started when interrupted
+// are left without an outcome
+pub fn run_graph(
+	nodes: &[ScheduledNode],
+	jobs: usize,
+	run_node: impl Fn(usize) -> Result<()> + Sync,
+) -> Vec<Option<Outcome>> {
+	let schedule = Mutex::new(Schedule {
+		pending: (0..nodes.len()).collect(),
+		outcomes: nodes.iter().map(|_| None).collect(),
+	});
+	let node_finished = Condvar::new();
+
+	std::thread::scope(|scope| {
+		for _ in 0..jobs.min(nodes.len()) {
+			scope.spawn(|| {
+				loop {
+					let mut current_schedule = schedule.lock().unwrap();
+
+					let node_index = loop {
+						if signals::check_interrupted().is_err() {
+							node_finished.notify_all();
+							return;
+						}
+
+						if let Some(node_index) = current_schedule.take_ready(nodes) {
+							break node_index;
+						}
+
+						if current_schedule.pending.is_empty() {
+							node_finished.notify_all();
+							return;
+						}
+
+						current_schedule = node_finished.wait(current_schedule).unwrap();
+					};
+
+					drop(current_schedule);
+
+					let outcome = match run_node(node_index) {
+						Ok(()) => Outcome::Passed,
+						Err(report) => Outcome::Failed(report),
+					};
+
+					schedule.lock().unwrap().outcomes[node_index] = Some(outcome);
+					node_finished.notify_all();
+				}
+			});
+		}
+	});
+
+	schedule.into_inner().unwrap().outcomes
+}
+
+#[cfg(test)]
+mod tests {
+	use color_eyre::eyre::eyre;
+
+	use super::*;
+
+	fn node(name: &str, dependencies: &[usize]) -> ScheduledNode {
+		ScheduledNode {
+			name: name.to_string(),
+			dependencies: dependencies.to_vec(),
+		}
+	}
+
+	fn describe(outcomes: &[Option<Outcome>]) -> Vec<String> {
+		outcomes
+			.iter()
+			.map(|outcome| match outcome {
+				Some(Outcome::Passed) => "passed".to_string(),
+				Some(Outcome::Failed(report)) => format!("failed: {report}"),
+				Some(Outcome::Blocked(name)) => format!("blocked by {name}"),
+				None => "not run".to_string(),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn runs_dependencies_first() {
+		let nodes = [node("app", &[1, 2]), node("lib", &[2]), node("core", &[])];
+		let order = Mutex::new(Vec::new());
+
+		let outcomes = run_graph(&nodes, 1, |node_index| {
+			order.lock().unwrap().push(nodes[node_index].name.as_str());
+			Ok(())
+		});
+
+		assert_eq!(order.into_inner().unwrap(), ["core", "lib", "app"]);
+		assert_eq!(describe(&outcomes), ["passed", "passed", "passed"]);
+	}
+
+	#[test]
+	fn blocks_nodes_depending_on_a_failure() {
+		let nodes = [
+			node("core", &[]),
+			node("lib", &[0]),
+			node("app", &[1]),
+			node("docs", &[]),
+		];
+
+		let outcomes = run_graph(&nodes, 2, |node_index| match node_index {
+			0 => Err(eyre!("exit code 1")),
+			_ => Ok(()),
+		});
+
+		assert_eq!(
+			describe(&outcomes),
+			[
+				"failed: exit code 1",
+				"blocked by core",
+				"blocked by lib",
+				"passed"
+			]
+		);
+	}
+}
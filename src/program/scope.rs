@@ -0,0 +1,40 @@
+use camino::Utf8PathBuf;
+use color_eyre::eyre::{Result, eyre};
+use serde_yaml::Mapping;
+
+use crate::{
+	config::{names, settings},
+	models::workspace,
+};
+
+// when run from inside a workspace the command is looked up in the workspace scope first, trying the
+// innermost workspace before falling back to the command as given
+pub fn infer_command(command: &str, config: &Mapping) -> Result<String> {
+	let workspaces = workspace::get_workspaces(config);
+
+	if workspaces.is_empty() {
+		return Ok(command.to_string());
+	}
+
+	let current_dir = Utf8PathBuf::from_path_buf(dunce::canonicalize(std::env::current_dir()?)?)
+		.map_err(|_| eyre!("unable to process non UTF-8 path"))?;
+
+	let containing_workspaces = workspace::get_containing_workspaces(&workspaces, &current_dir);
+
+	if containing_workspaces.is_empty() {
+		return Ok(command.to_string());
+	}
+
+	let command_names = names::get_command_names(config);
+	let scope_separator = settings::get().scope_separator.as_str();
+
+	for containing_workspace in containing_workspaces {
+		let scoped_command = format!("{}{scope_separator}{command}", containing_workspace.name);
+
+		if command_names.contains(&scoped_command) {
+			return Ok(scoped_command);
+		}
+	}
+
+	Ok(command.to_string())
+}
@@ -0,0 +1,73 @@
+use std::{
+	fmt,
+	sync::atomic::{AtomicU8, Ordering},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+	Debug = 0,
+	Info = 1,
+	Warn = 2,
+	Error = 3,
+}
+
+impl LogLevel {
+	pub const NAMES: [&str; 4] = ["debug", "info", "warn", "error"];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"debug" => Some(LogLevel::Debug),
+			"info" => Some(LogLevel::Info),
+			"warn" => Some(LogLevel::Warn),
+			"error" => Some(LogLevel::Error),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for LogLevel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", LogLevel::NAMES[*self as usize])
+	}
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_level(level: LogLevel) {
+	LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn is_enabled(level: LogLevel) -> bool {
+	level as u8 >= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+// logs go to stderr, so they never mix with the output of the tasks being run
+
+// traces of config processing and other internals
+#[macro_export]
+macro_rules! log_debug {
+	($($arg:tt)*) => {
+		if $crate::util::log::is_enabled($crate::util::log::LogLevel::Debug) {
+			eprintln!($($arg)*);
+		}
+	};
+}
+
+// progress of the commands being run
+#[macro_export]
+macro_rules! log_info {
+	($($arg:tt)*) => {
+		if $crate::util::log::is_enabled($crate::util::log::LogLevel::Info) {
+			eprintln!($($arg)*);
+		}
+	};
+}
+
+#[macro_export]
+macro_rules! log_warn {
+	($($arg:tt)*) => {
+		if $crate::util::log::is_enabled($crate::util::log::LogLevel::Warn) {
+			eprintln!($($arg)*);
+		}
+	};
+}
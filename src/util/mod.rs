@@ -1,2 +1,4 @@
+pub mod log;
 pub mod path;
+pub mod template;
 pub mod yaml;
@@ -35,7 +35,18 @@ pub fn try_get_path(value: &str, working_dir: Option<String>) -> Result<Option<U
 	Ok(Some(path))
 }
 
-fn looks_like_path(s: &str) -> bool {
+pub fn is_glob(value: &str) -> bool {
+	value.contains(['*', '?', '['])
+}
+
+// a path that depends on the directory it is resolved from, rather than being absolute or home based
+pub fn is_relative_path(value: &str) -> bool {
+	looks_like_path(value)
+		&& !Utf8Path::new(value).is_absolute()
+		&& !value.starts_with(['/', '~', '\\'])
+}
+
+pub fn looks_like_path(s: &str) -> bool {
 	// absolute (Unix) ("/...")
 	if s.starts_with('/') {
 		return true;
@@ -0,0 +1,159 @@
+use color_eyre::{Result, eyre::eyre};
+
+pub const PLACEHOLDER_START: &str = "{{";
+pub const PLACEHOLDER_END: &str = "}}";
+
+// `\{{` is rendered as a literal `{{`, for tasks that use the same syntax themselves, as in
+// `docker ps --format \{{.Names}}`
+pub const PLACEHOLDER_ESCAPE: char = '\\';
+
+pub fn has_placeholders(value: &str) -> bool {
+	let mut rest = value;
+
+	while let Some((start, escaped)) = find_placeholder_start(rest) {
+		if !escaped {
+			return true;
+		}
+
+		rest = &rest[start + PLACEHOLDER_START.len()..];
+	}
+
+	false
+}
+
+// the position of the next placeholder in the value, and whether it's escaped
+fn find_placeholder_start(value: &str) -> Option<(usize, bool)> {
+	let start = value.find(PLACEHOLDER_START)?;
+
+	Some((start, value[..start].ends_with(PLACEHOLDER_ESCAPE)))
+}
+
+// replaces every `{{ expression }}` placeholder in the template with the value the resolver returns
+// for the (trimmed) expression. resolved values are inserted as is and never scanned again
+pub fn render<F>(template: &str, mut resolve: F) -> Result<String>
+where
+	F: FnMut(&str) -> Result<String>,
+{
+	let mut rendered = String::with_capacity(template.len());
+	let mut rest = template;
+
+	while let Some((start, escaped)) = find_placeholder_start(rest) {
+		if escaped {
+			rendered.push_str(&rest[..start - PLACEHOLDER_ESCAPE.len_utf8()]);
+			rendered.push_str(PLACEHOLDER_START);
+			rest = &rest[start + PLACEHOLDER_START.len()..];
+
+			continue;
+		}
+
+		rendered.push_str(&rest[..start]);
+
+		let after_start = &rest[start + PLACEHOLDER_START.len()..];
+		let end = after_start
+			.find(PLACEHOLDER_END)
+			.ok_or_else(|| eyre!("unterminated placeholder in '{template}'"))?;
+
+		let expression = after_start[..end].trim();
+		if expression.is_empty() {
+			return Err(eyre!("empty placeholder in '{template}'"));
+		}
+
+		rendered.push_str(&resolve(expression)?);
+		rest = &after_start[end + PLACEHOLDER_END.len()..];
+	}
+
+	rendered.push_str(rest);
+
+	Ok(rendered)
+}
+
+// collects the (trimmed) expressions of every placeholder in the template
+pub fn get_expressions(template: &str) -> Vec<&str> {
+	let mut expressions = Vec::new();
+	let mut rest = template;
+
+	while let Some((start, escaped)) = find_placeholder_start(rest) {
+		let after_start = &rest[start + PLACEHOLDER_START.len()..];
+
+		if escaped {
+			rest = after_start;
+			continue;
+		}
+
+		let Some(end) = after_start.find(PLACEHOLDER_END) else {
+			break;
+		};
+
+		expressions.push(after_start[..end].trim());
+		rest = &after_start[end + PLACEHOLDER_END.len()..];
+	}
+
+	expressions
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn render_upper(template: &str) -> Result<String> {
+		render(template, |expression| Ok(expression.to_uppercase()))
+	}
+
+	#[test]
+	fn renders_trimmed_expressions() {
+		assert_eq!(render_upper("a {{ b }} c{{d}}").unwrap(), "a B cD");
+	}
+
+	#[test]
+	fn leaves_templates_without_placeholders_as_is() {
+		assert_eq!(render_upper("plain } text").unwrap(), "plain } text");
+	}
+
+	#[test]
+	fn does_not_rescan_resolved_values() {
+		let rendered = render("{{a}}", |_| Ok("{{b}}".to_string())).unwrap();
+
+		assert_eq!(rendered, "{{b}}");
+	}
+
+	#[test]
+	fn keeps_escaped_placeholders_literally() {
+		let template = "docker ps --format \\{{.Names}} {{ a }} $\\{{ b }}";
+
+		assert_eq!(
+			render_upper(template).unwrap(),
+			"docker ps --format {{.Names}} A ${{ b }}"
+		);
+		assert_eq!(get_expressions(template), ["a"]);
+		assert!(!has_placeholders("\\{{.Names}}"));
+	}
+
+	#[test]
+	fn rejects_unterminated_placeholders() {
+		let err = render_upper("a {{ b").unwrap_err();
+
+		assert!(err.to_string().contains("unterminated placeholder"));
+	}
+
+	#[test]
+	fn rejects_empty_placeholders() {
+		let err = render_upper("a {{  }}").unwrap_err();
+
+		assert!(err.to_string().contains("empty placeholder"));
+	}
+
+	#[test]
+	fn passes_resolver_errors_through() {
+		let err = render("{{a}}", |_| Err(eyre!("no value"))).unwrap_err();
+
+		assert_eq!(err.to_string(), "no value");
+	}
+
+	#[test]
+	fn collects_expressions() {
+		assert_eq!(
+			get_expressions("{{ a }} {{b | default: 1}} {{c"),
+			["a", "b | default: 1"]
+		);
+	}
+}